@@ -0,0 +1,50 @@
+use std::io;
+use std::time::{Duration, Instant};
+
+use simple_interpreter::{compiler::compile, vm::Vm, Evaluator, Parser, SimpleTokenizer, State};
+
+const SOURCE: &str = "
+var i = 0;
+var count = 0;
+while i < 300000 {
+    count = count + (i * 3 < 450000) - 1 + 1;
+    i = i + 1;
+}
+";
+
+fn measure(name: &str, run: impl Fn()) {
+    const ITERATIONS: u32 = 5;
+
+    let mut total = Duration::ZERO;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        run();
+        total += start.elapsed();
+    }
+
+    println!("{name}: {:?} per run", total / ITERATIONS);
+}
+
+fn main() {
+    let large_source = SOURCE.repeat(2000);
+    measure("parser", || {
+        Parser::new(SimpleTokenizer::new(&large_source))
+            .parse()
+            .unwrap();
+    });
+
+    let program = Parser::new(SimpleTokenizer::new(SOURCE)).parse().unwrap();
+    let instructions = compile(&program);
+
+    measure("evaluator", || {
+        let mut state = State::with_io(io::empty(), io::sink());
+        Evaluator::new()
+            .evaluate_with_state(&mut state, program.clone())
+            .unwrap();
+    });
+
+    measure("vm", || {
+        let mut state = State::with_io(io::empty(), io::sink());
+        Vm {}.execute(&mut state, &instructions).unwrap();
+    });
+}
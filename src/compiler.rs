@@ -0,0 +1,261 @@
+use crate::{
+    lexer::Operator,
+    parser::{AstNode, Expression, FunctionCall, Line, Pattern, Program},
+    symbol::Symbol,
+    utils::{Num, Span},
+};
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Instruction {
+    PushConst(Num),
+    Pop,
+    // Swaps the two values on top of the stack
+    Swap,
+    // Pushes a copy of the value below the top
+    Over,
+    LoadVar(Symbol, Span),
+    // Declares a new variable with `var`
+    DefineVar(Symbol, Span),
+    // Declares a new variable with `const`
+    DefineConst(Symbol, Span),
+    StoreVar(Symbol, Span),
+    // Operators keep the span of their expression for overflow errors
+    Add(Span),
+    Sub(Span),
+    Mul(Span),
+    Lt(Span),
+    BitAnd(Span),
+    BitOr(Span),
+    BitXor(Span),
+    Shl(Span),
+    Shr(Span),
+    JumpIfZero(usize),
+    // Jumps unless the value on top of the stack matches the pattern, the value is kept
+    JumpUnlessMatches(Pattern, usize),
+    Jump(usize),
+    // Loop bodies run in their own scope
+    EnterScope,
+    ExitScope,
+    // Reached an error node of a partial syntax tree
+    SyntaxError(Span),
+    // Reached an `include` that was not resolved by `include::Loader`
+    UnresolvedInclude(Symbol, Span),
+    // Built-in functions evaluate their own arguments, so the call keeps its syntax tree
+    Call(AstNode<FunctionCall>),
+}
+
+// Lowers the program into a flat list of instructions for the `vm`
+pub fn compile(AstNode { node: program, .. }: &AstNode<Program>) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    compile_lines(&mut instructions, &program.lines);
+    instructions
+}
+
+fn compile_lines(instructions: &mut Vec<Instruction>, lines: &[AstNode<Line>]) {
+    for line in lines {
+        compile_line(instructions, line);
+    }
+}
+
+fn compile_line(instructions: &mut Vec<Instruction>, AstNode { node: line, span }: &AstNode<Line>) {
+    match line {
+        Line::Assignment(name, expression) => {
+            compile_expression(instructions, expression);
+            instructions.push(Instruction::DefineVar(*name, *span));
+        }
+        Line::MultiAssignment(names, values) => {
+            for value in values {
+                compile_expression(instructions, value);
+            }
+            // The last value is on the top of the stack
+            for name in names.iter().rev() {
+                instructions.push(Instruction::DefineVar(*name, *span));
+            }
+        }
+        Line::ConstAssignment(name, expression) => {
+            compile_expression(instructions, expression);
+            instructions.push(Instruction::DefineConst(*name, *span));
+        }
+        Line::Reassignment(name, expression) => {
+            compile_expression(instructions, expression);
+            instructions.push(Instruction::StoreVar(*name, *span));
+        }
+        Line::Call(function_call) => {
+            instructions.push(Instruction::Call(function_call.clone()));
+            instructions.push(Instruction::Pop);
+        }
+        Line::Expression(expression) => {
+            compile_expression(instructions, expression);
+            instructions.push(Instruction::Pop);
+        }
+        Line::Loop(condition, lines) => {
+            let start = instructions.len();
+            compile_expression(instructions, condition);
+
+            let exit_jump = instructions.len();
+            instructions.push(Instruction::JumpIfZero(0));
+
+            instructions.push(Instruction::EnterScope);
+            compile_lines(instructions, lines);
+            instructions.push(Instruction::ExitScope);
+            instructions.push(Instruction::Jump(start));
+
+            instructions[exit_jump] = Instruction::JumpIfZero(instructions.len());
+        }
+        Line::Match(scrutinee, arms) => {
+            compile_expression(instructions, scrutinee);
+
+            let mut end_jumps = Vec::new();
+            for (pattern, lines) in arms {
+                let next_arm = instructions.len();
+                instructions.push(Instruction::JumpUnlessMatches(*pattern, 0));
+
+                instructions.push(Instruction::Pop);
+                instructions.push(Instruction::EnterScope);
+                compile_lines(instructions, lines);
+                instructions.push(Instruction::ExitScope);
+                end_jumps.push(instructions.len());
+                instructions.push(Instruction::Jump(0));
+
+                instructions[next_arm] =
+                    Instruction::JumpUnlessMatches(*pattern, instructions.len());
+            }
+
+            // No arm matched
+            instructions.push(Instruction::Pop);
+
+            for end_jump in end_jumps {
+                instructions[end_jump] = Instruction::Jump(instructions.len());
+            }
+        }
+        Line::DoWhile(lines, condition) => {
+            let start = instructions.len();
+            instructions.push(Instruction::EnterScope);
+            compile_lines(instructions, lines);
+            instructions.push(Instruction::ExitScope);
+
+            compile_expression(instructions, condition);
+            instructions.push(Instruction::JumpIfZero(instructions.len() + 2));
+            instructions.push(Instruction::Jump(start));
+        }
+        Line::Error => instructions.push(Instruction::SyntaxError(*span)),
+        Line::Include(path) => instructions.push(Instruction::UnresolvedInclude(*path, *span)),
+    }
+}
+
+fn compile_expression(
+    instructions: &mut Vec<Instruction>,
+    AstNode {
+        node: expression,
+        span,
+    }: &AstNode<Expression>,
+) {
+    match expression {
+        Expression::Number(value) => instructions.push(Instruction::PushConst(*value)),
+        Expression::Bool(value) => instructions.push(Instruction::PushConst(*value as Num)),
+        Expression::Identifier(name) => instructions.push(Instruction::LoadVar(*name, *span)),
+        Expression::Error => instructions.push(Instruction::SyntaxError(*span)),
+        Expression::Call(function_call) => {
+            instructions.push(Instruction::Call(function_call.clone()));
+        }
+        Expression::Conditional(condition, then, otherwise) => {
+            compile_expression(instructions, condition);
+
+            let else_jump = instructions.len();
+            instructions.push(Instruction::JumpIfZero(0));
+            compile_expression(instructions, then);
+
+            let end_jump = instructions.len();
+            instructions.push(Instruction::Jump(0));
+            instructions[else_jump] = Instruction::JumpIfZero(instructions.len());
+            compile_expression(instructions, otherwise);
+
+            instructions[end_jump] = Instruction::Jump(instructions.len());
+        }
+        Expression::Comparison(first, comparisons) => {
+            compile_expression(instructions, first);
+
+            // Every comparison but the last keeps its right operand for the next one and jumps
+            // to `false` when it fails
+            let mut fail_jumps = Vec::new();
+            for (index, (operator, operand)) in comparisons.iter().enumerate() {
+                compile_expression(instructions, operand);
+                if index + 1 < comparisons.len() {
+                    instructions.push(Instruction::Swap);
+                    instructions.push(Instruction::Over);
+                    instructions.push(operator_instruction(*operator, *span));
+                    fail_jumps.push(instructions.len());
+                    instructions.push(Instruction::JumpIfZero(0));
+                } else {
+                    instructions.push(operator_instruction(*operator, *span));
+                }
+            }
+
+            let end_jump = instructions.len();
+            instructions.push(Instruction::Jump(0));
+            for fail_jump in fail_jumps {
+                instructions[fail_jump] = Instruction::JumpIfZero(instructions.len());
+            }
+            instructions.push(Instruction::Pop);
+            instructions.push(Instruction::PushConst(0));
+
+            instructions[end_jump] = Instruction::Jump(instructions.len());
+        }
+        Expression::Assign(name, value) => {
+            compile_expression(instructions, value);
+            instructions.push(Instruction::StoreVar(*name, *span));
+            instructions.push(Instruction::LoadVar(*name, *span));
+        }
+        Expression::BinaryOperator(left, operator, right) => {
+            compile_expression(instructions, left);
+            compile_expression(instructions, right);
+            instructions.push(operator_instruction(*operator, *span));
+        }
+    }
+}
+
+fn operator_instruction(operator: Operator, span: Span) -> Instruction {
+    match operator {
+        Operator::Plus => Instruction::Add(span),
+        Operator::Minus => Instruction::Sub(span),
+        Operator::Multiplication => Instruction::Mul(span),
+        Operator::LessThan => Instruction::Lt(span),
+        Operator::BitwiseAnd => Instruction::BitAnd(span),
+        Operator::BitwiseOr => Instruction::BitOr(span),
+        Operator::BitwiseXor => Instruction::BitXor(span),
+        Operator::ShiftLeft => Instruction::Shl(span),
+        Operator::ShiftRight => Instruction::Shr(span),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{lexer::SimpleTokenizer, parser::Parser, utils::Span};
+
+    use super::{compile, Instruction};
+
+    #[test]
+    fn compile_loop() {
+        let program = Parser::new(SimpleTokenizer::new("while i < 3 { i = i + 1; }"))
+            .parse()
+            .unwrap();
+        let span = |start, end| Span { start, end };
+
+        assert_eq!(
+            compile(&program),
+            [
+                Instruction::LoadVar("i".into(), span(6, 7)),
+                Instruction::PushConst(3),
+                Instruction::Lt(span(6, 11)),
+                Instruction::JumpIfZero(11),
+                Instruction::EnterScope,
+                Instruction::LoadVar("i".into(), span(18, 19)),
+                Instruction::PushConst(1),
+                Instruction::Add(span(18, 23)),
+                Instruction::StoreVar("i".into(), span(14, 24)),
+                Instruction::ExitScope,
+                Instruction::Jump(0),
+            ]
+        );
+    }
+}
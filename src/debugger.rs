@@ -0,0 +1,71 @@
+use std::io::{BufRead, Write};
+
+use crate::{
+    evaluator::{Evaluator, State},
+    parser::{AstNode, Program},
+    runtime::format_integer,
+    utils::format_error,
+};
+
+// Runs the program one top-level statement at a time. Commands are read from `commands`
+// and answered on `output`, while the program itself keeps using the streams of `state`.
+pub fn run_debugger(
+    source: &str,
+    AstNode { node: program, .. }: AstNode<Program>,
+    state: &mut State,
+    evaluator: &Evaluator,
+    commands: &mut impl BufRead,
+    output: &mut impl Write,
+) -> Result<(), String> {
+    let write_error = |err: std::io::Error| format!("Can not write to the console: {err}");
+    let mut continuing = false;
+
+    state.steps = 0;
+    state.exit_code = None;
+    for line in program.lines {
+        if !continuing {
+            writeln!(output, "{}", &source[line.span.start..line.span.end]).map_err(write_error)?;
+        }
+
+        while !continuing {
+            write!(output, "(debug) ")
+                .and_then(|_| output.flush())
+                .map_err(write_error)?;
+
+            let mut command = String::new();
+            let read = commands
+                .read_line(&mut command)
+                .map_err(|err| format!("Can not read from the console: {err}"))?;
+
+            // Ctrl-D
+            if read == 0 {
+                return writeln!(output).map_err(write_error);
+            }
+
+            match command.split_whitespace().collect::<Vec<&str>>().as_slice() {
+                ["step"] => break,
+                ["continue"] => continuing = true,
+                ["quit"] => return Ok(()),
+                ["print", name] => match state.variable(name) {
+                    Some(value) => {
+                        let value = format_integer(value, state.output_radix);
+                        writeln!(output, "{name} = {value}")
+                    }
+                    None => writeln!(output, "Variable {name} is not defined"),
+                }
+                .map_err(write_error)?,
+                _ => writeln!(output, "Commands: step, continue, print <variable>, quit")
+                    .map_err(write_error)?,
+            }
+        }
+
+        let result = evaluator.evaluate_line(state, &line);
+        let result = state.finish(result);
+        if state.exit_code.is_some() {
+            return Ok(());
+        }
+        result.map_err(|ref err| format_error(err, source))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,281 @@
+use crate::parser::{AstNode, Expression, FunctionCall, Line, Program};
+
+const INDENT: &str = "  ";
+
+// A node of the syntax tree reduced to its label, shared by the text and the DOT output
+struct Node {
+    label: String,
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn new(label: impl Into<String>, children: Vec<Node>) -> Node {
+        Node {
+            label: label.into(),
+            children,
+        }
+    }
+
+    fn leaf(label: impl Into<String>) -> Node {
+        Node::new(label, Vec::new())
+    }
+}
+
+// The syntax tree as indented text, one node per line with its children below it, like
+//
+// Assignment x
+//   BinaryOperator +
+//     Number 1
+//     Number 2
+pub fn dump(program: &AstNode<Program>) -> String {
+    let mut output = String::new();
+    write_text(&mut output, &program_node(program), 0);
+    output
+}
+
+pub fn dump_expression(expression: &AstNode<Expression>) -> String {
+    let mut output = String::new();
+    write_text(&mut output, &expression_node(expression), 0);
+    output
+}
+
+// The syntax tree as a GraphViz digraph, every node is numbered in the order it is visited
+//
+// digraph ast {
+//   n0 [label="Program"];
+//   n1 [label="Assignment x"];
+//   n0 -> n1;
+// }
+pub fn dot(program: &AstNode<Program>) -> String {
+    let mut output = String::from("digraph ast {\n");
+    write_dot(&mut output, &program_node(program), &mut 0);
+    output.push_str("}\n");
+    output
+}
+
+fn write_text(output: &mut String, node: &Node, depth: usize) {
+    output.push_str(&INDENT.repeat(depth));
+    output.push_str(&node.label);
+    output.push('\n');
+
+    for child in &node.children {
+        write_text(output, child, depth + 1);
+    }
+}
+
+// Returns the id of the written node
+fn write_dot(output: &mut String, node: &Node, next_id: &mut usize) -> usize {
+    let id = *next_id;
+    *next_id += 1;
+
+    let label = node.label.replace('\\', "\\\\").replace('"', "\\\"");
+    output.push_str(&format!("{INDENT}n{id} [label=\"{label}\"];\n"));
+
+    for child in &node.children {
+        let child_id = write_dot(output, child, next_id);
+        output.push_str(&format!("{INDENT}n{id} -> n{child_id};\n"));
+    }
+
+    id
+}
+
+fn program_node(AstNode { node: program, .. }: &AstNode<Program>) -> Node {
+    Node::new("Program", line_nodes(&program.lines))
+}
+
+fn line_nodes(lines: &[AstNode<Line>]) -> Vec<Node> {
+    lines.iter().map(line_node).collect()
+}
+
+fn line_node(AstNode { node: line, .. }: &AstNode<Line>) -> Node {
+    match line {
+        Line::Assignment(name, expression) => Node::new(
+            format!("Assignment {name}"),
+            vec![expression_node(expression)],
+        ),
+        Line::MultiAssignment(names, values) => Node::new(
+            format!(
+                "MultiAssignment {}",
+                names
+                    .iter()
+                    .map(|name| name.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            values.iter().map(expression_node).collect(),
+        ),
+        Line::ConstAssignment(name, expression) => Node::new(
+            format!("ConstAssignment {name}"),
+            vec![expression_node(expression)],
+        ),
+        Line::Reassignment(name, expression) => Node::new(
+            format!("Reassignment {name}"),
+            vec![expression_node(expression)],
+        ),
+        Line::Call(function_call) => function_call_node(function_call),
+        Line::Expression(expression) => Node::new("Expression", vec![expression_node(expression)]),
+        Line::Loop(condition, lines) => Node::new(
+            "Loop",
+            vec![
+                expression_node(condition),
+                Node::new("Body", line_nodes(lines)),
+            ],
+        ),
+        Line::Match(scrutinee, arms) => {
+            let mut children = vec![expression_node(scrutinee)];
+            children
+                .extend(arms.iter().map(|(pattern, lines)| {
+                    Node::new(format!("Arm {pattern}"), line_nodes(lines))
+                }));
+            Node::new("Match", children)
+        }
+        Line::DoWhile(lines, condition) => Node::new(
+            "DoWhile",
+            vec![
+                Node::new("Body", line_nodes(lines)),
+                expression_node(condition),
+            ],
+        ),
+        Line::Include(path) => Node::leaf(format!("Include {path}")),
+        Line::Error => Node::leaf("Error"),
+    }
+}
+
+fn expression_node(
+    AstNode {
+        node: expression, ..
+    }: &AstNode<Expression>,
+) -> Node {
+    match expression {
+        Expression::Number(value) => Node::leaf(format!("Number {value}")),
+        Expression::Bool(value) => Node::leaf(format!("Bool {value}")),
+        Expression::Identifier(name) => Node::leaf(format!("Identifier {name}")),
+        Expression::Call(function_call) => function_call_node(function_call),
+        Expression::Assign(name, value) => {
+            Node::new(format!("Assign {name}"), vec![expression_node(value)])
+        }
+        Expression::Conditional(condition, then, otherwise) => Node::new(
+            "Conditional",
+            vec![
+                expression_node(condition),
+                expression_node(then),
+                expression_node(otherwise),
+            ],
+        ),
+        Expression::BinaryOperator(left, operator, right) => Node::new(
+            format!("BinaryOperator {operator}"),
+            vec![expression_node(left), expression_node(right)],
+        ),
+        Expression::Comparison(first, comparisons) => {
+            let mut children = vec![expression_node(first)];
+            for (operator, operand) in comparisons {
+                children.push(Node::leaf(format!("Operator {operator}")));
+                children.push(expression_node(operand));
+            }
+            Node::new("Comparison", children)
+        }
+        Expression::Error => Node::leaf("Error"),
+    }
+}
+
+fn function_call_node(
+    AstNode {
+        node: function_call,
+        ..
+    }: &AstNode<FunctionCall>,
+) -> Node {
+    Node::new(
+        format!("Call {}", function_call.name),
+        function_call
+            .arguments
+            .iter()
+            .map(expression_node)
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{lexer::SimpleTokenizer, parser::Parser};
+
+    use super::{dot, dump, dump_expression};
+
+    #[test]
+    fn precedence() {
+        let expression = Parser::new(SimpleTokenizer::new("1 + 2 * 3"))
+            .parse_single_expression()
+            .unwrap();
+
+        assert_eq!(
+            dump_expression(&expression),
+            "\
+BinaryOperator +
+  Number 1
+  BinaryOperator *
+    Number 2
+    Number 3
+"
+        );
+    }
+
+    #[test]
+    fn program() {
+        let program = Parser::new(SimpleTokenizer::new(
+            "var i = 0; while i < 3 { print(i, true); i = i + 1; }",
+        ))
+        .parse()
+        .unwrap();
+
+        assert_eq!(
+            dump(&program),
+            "\
+Program
+  Assignment i
+    Number 0
+  Loop
+    BinaryOperator <
+      Identifier i
+      Number 3
+    Body
+      Call print
+        Identifier i
+        Bool true
+      Reassignment i
+        BinaryOperator +
+          Identifier i
+          Number 1
+"
+        );
+    }
+
+    #[test]
+    fn graphviz() {
+        let program = Parser::new(SimpleTokenizer::new("print(1 + 2 * 3);"))
+            .parse()
+            .unwrap();
+        let output = dot(&program);
+
+        assert_eq!(
+            output,
+            "\
+digraph ast {
+  n0 [label=\"Program\"];
+  n1 [label=\"Call print\"];
+  n2 [label=\"BinaryOperator +\"];
+  n3 [label=\"Number 1\"];
+  n2 -> n3;
+  n4 [label=\"BinaryOperator *\"];
+  n5 [label=\"Number 2\"];
+  n4 -> n5;
+  n6 [label=\"Number 3\"];
+  n4 -> n6;
+  n2 -> n4;
+  n1 -> n2;
+  n0 -> n1;
+}
+"
+        );
+        // A tree has one edge less than nodes
+        assert_eq!(output.matches(" -> ").count(), 6);
+    }
+}
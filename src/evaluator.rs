@@ -1,91 +1,485 @@
 use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
 
 use crate::lexer::Operator;
-use crate::runtime::{function_input, function_print};
-use crate::utils::SpanError;
+use crate::runtime::{
+    function_abs, function_assert, function_defined, function_exit, function_fact, function_input,
+    function_input_radix, function_max, function_min, function_print, function_print_bin,
+    function_print_hex, function_rand, function_set_radix, function_var_count, function_write,
+    LineCallback, Random,
+};
+use crate::symbol::Symbol;
+use crate::utils::{Num, Span, SpanError};
 
 use crate::parser::{AstNode, Expression, FunctionCall, Line, Program};
 
 pub type RuntimeError = SpanError;
 
-type CustomFunction =
-    fn(&Evaluator, &mut State, &AstNode<FunctionCall>) -> Result<i32, RuntimeError>;
+pub type CustomFunction = fn(&Evaluator, &mut State, &AstNode<FunctionCall>) -> Result<Num, Stop>;
 
-pub struct State {
-    pub variables: HashMap<String, i32>,
-    pub functions: HashMap<String, CustomFunction>,
+// Why a line or expression stopped before producing a value. `?` turns a `RuntimeError` into
+// `Stop::Error`, so functions mostly only deal with errors.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Stop {
+    Error(RuntimeError),
+    // `exit` was called with the code, this ends the program without failing it
+    Exit(Num),
 }
 
-pub struct Evaluator {}
+impl From<RuntimeError> for Stop {
+    fn from(error: RuntimeError) -> Self {
+        Stop::Error(error)
+    }
+}
+
+// Variables in nested scopes, the innermost scope is last. Loop bodies get a new scope on each
+// iteration, so variables declared inside them do not outlive the iteration.
+pub struct Variables {
+    scopes: Vec<HashMap<Symbol, Binding>>,
+}
+
+struct Binding {
+    value: Num,
+    // Declared with `const`
+    constant: bool,
+}
+
+impl Variables {
+    pub fn new() -> Variables {
+        Variables {
+            scopes: vec![HashMap::new()],
+        }
+    }
+
+    // Inner scopes shadow outer ones
+    pub fn get(&self, name: &Symbol) -> Option<&Num> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .map(|binding| &binding.value)
+    }
+
+    // Declares the variable in the innermost scope, returns false if it is already declared there
+    pub fn define(&mut self, name: Symbol, value: Num) -> bool {
+        self.declare(name, value, false)
+    }
+
+    pub fn define_constant(&mut self, name: Symbol, value: Num) -> bool {
+        self.declare(name, value, true)
+    }
+
+    fn declare(&mut self, name: Symbol, value: Num, constant: bool) -> bool {
+        let scope = self
+            .scopes
+            .last_mut()
+            .expect("the global scope is never popped");
+        if scope.contains_key(&name) {
+            return false;
+        }
+        scope.insert(name, Binding { value, constant });
+        true
+    }
+
+    // Updates an existing variable, `span` is used for the error if it is missing or constant
+    pub fn assign(&mut self, name: Symbol, value: Num, span: Span) -> Result<(), RuntimeError> {
+        let binding = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(&name))
+            .ok_or_else(|| RuntimeError {
+                message: format!("Variable {name} is not defined"),
+                span,
+            })?;
+
+        if binding.constant {
+            return Err(RuntimeError {
+                message: format!("Cannot reassign const {name}"),
+                span,
+            });
+        }
+        binding.value = value;
+        Ok(())
+    }
+
+    pub fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    pub fn pop_scope(&mut self) {
+        if self.scopes.len() > 1 {
+            self.scopes.pop();
+        }
+    }
+
+    // Number of scopes, including the global one
+    pub fn depth(&self) -> usize {
+        self.scopes.len()
+    }
+
+    // Pops scopes until only `depth` of them are left, used to clean up after an error
+    pub fn truncate(&mut self, depth: usize) {
+        self.scopes.truncate(depth.max(1));
+    }
+
+    // Visible variables, shadowed ones are left out
+    pub fn iter(&self) -> impl Iterator<Item = (&Symbol, &Num)> {
+        self.scopes
+            .iter()
+            .enumerate()
+            .flat_map(move |(index, scope)| {
+                scope
+                    .iter()
+                    .filter(move |(name, _)| {
+                        !self.scopes[index + 1..]
+                            .iter()
+                            .any(|inner| inner.contains_key(name))
+                    })
+                    .map(|(name, binding)| (name, &binding.value))
+            })
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for Variables {
+    fn default() -> Self {
+        Variables::new()
+    }
+}
+
+// `'a` is the lifetime of the streams, so the output can borrow from the host, like a callback
+// writing to a local buffer. States with stdin and stdout are `State<'static>`.
+pub struct State<'a> {
+    pub variables: Variables,
+    pub functions: HashMap<Symbol, CustomFunction>,
+    // Radix used by `print` for integers: 2, 10 or 16
+    pub output_radix: u32,
+    // Streams used by the built-in functions, stdin and stdout by default
+    pub input: Box<dyn BufRead + 'a>,
+    pub output: Box<dyn Write + 'a>,
+    // Lines and loop iterations evaluated by the current program
+    pub steps: u64,
+    // Generator of `rand`, seeded from the clock unless `set_seed` is called
+    pub random: Random,
+    // Set when the program called `exit`, the code the process should exit with
+    pub exit_code: Option<Num>,
+}
+
+impl State<'static> {
+    pub fn new() -> State<'static> {
+        State::with_io(BufReader::new(io::stdin()), io::stdout())
+    }
+}
+
+impl<'a> State<'a> {
+    pub fn with_io(input: impl BufRead + 'a, output: impl Write + 'a) -> State<'a> {
+        State {
+            variables: Variables::new(),
+            functions: builtin_functions(),
+            output_radix: 10,
+            input: Box::new(input),
+            output: Box::new(output),
+            steps: 0,
+            random: Random::from_time(),
+            exit_code: None,
+        }
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        self.random = Random::new(seed);
+    }
+
+    // Makes a host function callable from scripts, replacing any function with the same name
+    pub fn register_function(&mut self, name: &str, function: CustomFunction) {
+        self.functions.insert(Symbol::intern(name), function);
+    }
+
+    pub fn variable(&self, name: &str) -> Option<Num> {
+        self.variables.get(&Symbol::intern(name)).copied()
+    }
+
+    // Ends a program, `Stop::Exit` keeps its code in `exit_code` and counts as success
+    pub fn finish<T: Default>(&mut self, result: Result<T, Stop>) -> Result<T, RuntimeError> {
+        match result {
+            Ok(value) => Ok(value),
+            Err(Stop::Error(error)) => Err(error),
+            Err(Stop::Exit(code)) => {
+                self.exit_code = Some(code);
+                Ok(T::default())
+            }
+        }
+    }
+}
+
+// The functions every state starts with
+pub fn builtin_functions() -> HashMap<Symbol, CustomFunction> {
+    HashMap::from([
+        ("input".into(), function_input as CustomFunction),
+        ("input_radix".into(), function_input_radix as CustomFunction),
+        ("print".into(), function_print as CustomFunction),
+        ("write".into(), function_write as CustomFunction),
+        ("print_hex".into(), function_print_hex as CustomFunction),
+        ("print_bin".into(), function_print_bin as CustomFunction),
+        ("set_radix".into(), function_set_radix as CustomFunction),
+        ("fact".into(), function_fact as CustomFunction),
+        ("abs".into(), function_abs as CustomFunction),
+        ("min".into(), function_min as CustomFunction),
+        ("max".into(), function_max as CustomFunction),
+        ("rand".into(), function_rand as CustomFunction),
+        ("assert".into(), function_assert as CustomFunction),
+        ("var_count".into(), function_var_count as CustomFunction),
+        ("defined".into(), function_defined as CustomFunction),
+        ("exit".into(), function_exit as CustomFunction),
+    ])
+}
+
+impl Default for State<'static> {
+    fn default() -> Self {
+        State::new()
+    }
+}
+
+pub fn evaluate_operator(
+    operator: Operator,
+    left: Num,
+    right: Num,
+    span: Span,
+) -> Result<Num, RuntimeError> {
+    match operator {
+        Operator::ShiftLeft | Operator::ShiftRight if !(0..Num::BITS as Num).contains(&right) => {
+            return Err(RuntimeError {
+                message: format!(
+                    "Shift amount out of range: {left} {} {right}, expected 0 to {}",
+                    operator.symbol(),
+                    Num::BITS - 1
+                ),
+                span,
+            })
+        }
+        _ => {}
+    }
+
+    match operator {
+        Operator::Plus => left.checked_add(right),
+        Operator::Minus => left.checked_sub(right),
+        Operator::Multiplication => left.checked_mul(right),
+        Operator::LessThan => Some((left < right) as Num),
+        Operator::BitwiseAnd => Some(left & right),
+        Operator::BitwiseOr => Some(left | right),
+        Operator::BitwiseXor => Some(left ^ right),
+        // Bits shifted out are dropped, the right shift keeps the sign
+        Operator::ShiftLeft => Some(left << right),
+        Operator::ShiftRight => Some(left >> right),
+    }
+    .ok_or_else(|| RuntimeError {
+        message: format!("Integer overflow: {left} {} {right}", operator.symbol()),
+        span,
+    })
+}
+
+// Error nodes come from `Parser::parse_partial`, they can not be run
+pub fn syntax_error(span: Span) -> RuntimeError {
+    RuntimeError {
+        message: "Cannot run code that failed to parse".into(),
+        span,
+    }
+}
+
+// Includes are replaced with the included lines by `include::Loader`, the parser alone keeps them
+pub fn unresolved_include(path: Symbol, span: Span) -> RuntimeError {
+    RuntimeError {
+        message: format!("Cannot include {path}, the program was not loaded from a file"),
+        span,
+    }
+}
+
+#[derive(Default)]
+pub struct Evaluator {
+    // Steps allowed for a single program, unlimited when None
+    max_steps: Option<u64>,
+}
 
 impl Evaluator {
-    pub fn evaluate(
+    pub fn new() -> Evaluator {
+        Evaluator::default()
+    }
+
+    // Stops runaway programs, like `while 1 { }`, after `max_steps` lines and loop iterations
+    pub fn with_max_steps(mut self, max_steps: u64) -> Evaluator {
+        self.max_steps = Some(max_steps);
+        self
+    }
+
+    pub fn evaluate(&self, program: AstNode<Program>) -> Result<(), RuntimeError> {
+        let mut state = State::new();
+        self.evaluate_with_state(&mut state, program)
+    }
+
+    // Runs the program with its output passed to `output` line by line while it runs, so a host
+    // can show the output of a long program as it is produced. Input is read from stdin, a host
+    // with its own input gives `State::with_io` a `LineCallback` and calls `evaluate_with_state`.
+    pub fn evaluate_with(
         &self,
+        program: AstNode<Program>,
+        output: &mut dyn FnMut(&str),
+    ) -> Result<(), RuntimeError> {
+        let mut state = State::with_io(BufReader::new(io::stdin()), LineCallback::new(output));
+        let result = self.evaluate_with_state(&mut state, program);
+        // Passes on the text of a last `write` without a line break, this can not fail
+        let _ = state.output.flush();
+        result
+    }
+
+    // Keeps variables and functions in `state`, so it can be reused between programs
+    pub fn evaluate_with_state(
+        &self,
+        state: &mut State,
         AstNode {
             node: program,
             span: _,
         }: AstNode<Program>,
     ) -> Result<(), RuntimeError> {
-        let mut state = State {
-            variables: HashMap::new(),
-            functions: HashMap::from([
-                ("input".into(), function_input as CustomFunction),
-                ("print".into(), function_print as CustomFunction),
-            ]),
-        };
-        for line in program.lines {
-            self.evaluate_line(&mut state, &line)?
+        state.steps = 0;
+        state.exit_code = None;
+        let result = program
+            .lines
+            .iter()
+            .try_for_each(|line| self.evaluate_line(state, line));
+        state.finish(result)
+    }
+
+    fn step(&self, state: &mut State, span: Span) -> Result<(), RuntimeError> {
+        state.steps += 1;
+
+        match self.max_steps {
+            Some(max_steps) if state.steps > max_steps => Err(RuntimeError {
+                message: "Execution budget exceeded".into(),
+                span,
+            }),
+            _ => Ok(()),
         }
-        Ok(())
     }
 
-    fn evaluate_line(
+    pub fn evaluate_line(
         &self,
         state: &mut State,
         AstNode { node: line, span }: &AstNode<Line>,
-    ) -> Result<(), RuntimeError> {
+    ) -> Result<(), Stop> {
+        self.step(state, *span)?;
+
         match line {
             Line::Assignment(name, expression) => {
                 let value = self.evaluate_expression(state, expression)?;
 
-                // use of unstable library feature 'map_try_insert'
-                if state.variables.contains_key(name) {
+                if !state.variables.define(*name, value) {
                     return Err(RuntimeError {
                         message: format!("Variable {name} is already defined"),
                         span: *span,
-                    });
+                    }
+                    .into());
+                };
+                Ok(())
+            }
+            Line::MultiAssignment(names, values) => {
+                let values = values
+                    .iter()
+                    .map(|value| self.evaluate_expression(state, value))
+                    .collect::<Result<Vec<Num>, Stop>>()?;
+
+                for (name, value) in names.iter().zip(values) {
+                    if !state.variables.define(*name, value) {
+                        return Err(RuntimeError {
+                            message: format!("Variable {name} is already defined"),
+                            span: *span,
+                        }
+                        .into());
+                    }
+                }
+                Ok(())
+            }
+            Line::ConstAssignment(name, expression) => {
+                let value = self.evaluate_expression(state, expression)?;
+
+                if !state.variables.define_constant(*name, value) {
+                    return Err(RuntimeError {
+                        message: format!("Variable {name} is already defined"),
+                        span: *span,
+                    }
+                    .into());
                 };
-                state.variables.insert(name.clone(), value);
                 Ok(())
             }
             Line::Reassignment(name, expression) => {
                 let value = self.evaluate_expression(state, expression)?;
-                let var_ref = state.variables.get_mut(name).ok_or_else(|| RuntimeError {
-                    message: format!("Variable {name} is not defined"),
-                    span: *span,
-                })?;
-                *var_ref = value;
+                Ok(state.variables.assign(*name, value, *span)?)
+            }
+            Line::Expression(expression) => {
+                self.evaluate_expression(state, expression)?;
                 Ok(())
             }
             Line::Call(function_call) => self
                 .evaluate_function_call(state, function_call)
                 .map(|_| ()),
+            Line::Error => Err(syntax_error(*span).into()),
+            Line::Include(path) => Err(unresolved_include(*path, *span).into()),
             Line::Loop(condition, lines) => {
                 while self.evaluate_expression(state, condition)? != 0 {
-                    for line in lines {
-                        self.evaluate_line(state, line)?;
-                    }
+                    self.step(state, *span)?;
+
+                    state.variables.push_scope();
+                    let result = lines
+                        .iter()
+                        .try_for_each(|line| self.evaluate_line(state, line));
+                    state.variables.pop_scope();
+                    result?;
                 }
                 Ok(())
             }
+            Line::Match(scrutinee, arms) => {
+                let value = self.evaluate_expression(state, scrutinee)?;
+
+                let Some((_, lines)) = arms.iter().find(|(pattern, _)| pattern.matches(value))
+                else {
+                    return Ok(());
+                };
+
+                state.variables.push_scope();
+                let result = lines
+                    .iter()
+                    .try_for_each(|line| self.evaluate_line(state, line));
+                state.variables.pop_scope();
+                result
+            }
+            Line::DoWhile(lines, condition) => loop {
+                self.step(state, *span)?;
+
+                state.variables.push_scope();
+                let result = lines
+                    .iter()
+                    .try_for_each(|line| self.evaluate_line(state, line));
+                state.variables.pop_scope();
+                result?;
+
+                if self.evaluate_expression(state, condition)? == 0 {
+                    return Ok(());
+                }
+            },
         }
     }
 
-    fn evaluate_function_call(
+    pub fn evaluate_function_call(
         &self,
         state: &mut State,
         ast_node: &AstNode<FunctionCall>,
-    ) -> Result<i32, RuntimeError> {
+    ) -> Result<Num, Stop> {
         state
             .functions
             .get(&ast_node.node.name)
@@ -95,15 +489,6 @@ impl Evaluator {
             })?(self, state, ast_node)
     }
 
-    fn evaluate_operator(&self, operator: Operator, left: i32, right: i32) -> i32 {
-        match operator {
-            Operator::Plus => left + right,
-            Operator::Minus => left - right,
-            Operator::Multiplication => left * right,
-            Operator::LessThan => (left < right) as i32,
-        }
-    }
-
     pub fn evaluate_expression(
         &self,
         state: &mut State,
@@ -111,24 +496,61 @@ impl Evaluator {
             node: expression,
             span,
         }: &AstNode<Expression>,
-    ) -> Result<i32, RuntimeError> {
+    ) -> Result<Num, Stop> {
         match expression {
             Expression::Number(value) => Ok(*value),
+            Expression::Bool(value) => Ok(*value as Num),
+            Expression::Error => Err(syntax_error(*span).into()),
             Expression::Call(function_call) => self.evaluate_function_call(state, function_call),
             Expression::BinaryOperator(left, op, right) => {
-                let left_value = self.evaluate_expression(state, left)?;
-                let right_value = self.evaluate_expression(state, right)?;
-                Ok(self.evaluate_operator(*op, left_value, right_value))
+                // Left associative chains like `1 + 2 + 3` nest to the left, walking down the
+                // left side with a loop keeps long chains from overflowing the stack
+                let mut operations = vec![(*op, right, *span)];
+                let mut leftmost = left;
+                while let Expression::BinaryOperator(left, op, right) = &leftmost.node {
+                    operations.push((*op, right, leftmost.span));
+                    leftmost = left;
+                }
+
+                let mut value = self.evaluate_expression(state, leftmost)?;
+                for (op, right, span) in operations.into_iter().rev() {
+                    let right_value = self.evaluate_expression(state, right)?;
+                    value = evaluate_operator(op, value, right_value, span)?;
+                }
+                Ok(value)
+            }
+            Expression::Comparison(first, comparisons) => {
+                let mut left = self.evaluate_expression(state, first)?;
+                for (op, operand) in comparisons {
+                    let right = self.evaluate_expression(state, operand)?;
+                    if evaluate_operator(*op, left, right, *span)? == 0 {
+                        return Ok(0);
+                    }
+                    left = right;
+                }
+                Ok(1)
+            }
+            Expression::Conditional(condition, then, otherwise) => {
+                if self.evaluate_expression(state, condition)? != 0 {
+                    self.evaluate_expression(state, then)
+                } else {
+                    self.evaluate_expression(state, otherwise)
+                }
+            }
+            Expression::Assign(name, value) => {
+                let value = self.evaluate_expression(state, value)?;
+                state.variables.assign(*name, value, *span)?;
+                Ok(value)
             }
             Expression::Identifier(name) => {
-                state
+                Ok(state
                     .variables
                     .get(name)
                     .copied()
                     .ok_or_else(|| RuntimeError {
-                        message: format!("Variable does not exist: {name}"),
+                        message: format!("Variable {name} is not defined"),
                         span: *span,
-                    })
+                    })?)
             }
         }
     }
@@ -136,10 +558,12 @@ impl Evaluator {
 
 #[cfg(test)]
 mod tests {
-    use crate::lexer::Operator;
-    use crate::parser::{AstNode, Expression};
-    use crate::utils::Span;
-    use std::collections::HashMap;
+    use crate::lexer::{Operator, SimpleTokenizer};
+    use crate::parser::{AstNode, Expression, FunctionCall, Parser};
+    use crate::run_with_state;
+    use crate::runtime::{LineCallback, SharedBuffer};
+    use crate::utils::{Num, Span};
+    use std::io::Cursor;
 
     use super::{Evaluator, State};
 
@@ -159,14 +583,557 @@ mod tests {
             Operator::Plus,
             Box::new(ast!(Expression::Number(2))),
         ));
-        let mut state = State {
-            variables: HashMap::new(),
-            functions: HashMap::new(),
-        };
-        let evaluator = Evaluator {};
+        let mut state = State::new();
+        let evaluator = Evaluator::new();
         let result = evaluator.evaluate_expression(&mut state, &ast).unwrap();
         let expected = 3;
 
         assert_eq!(expected, result);
     }
+
+    #[test]
+    fn test_evaluate_deep_expression() {
+        let mut ast = ast!(Expression::Number(0));
+        for _ in 0..50_000 {
+            ast = ast!(Expression::BinaryOperator(
+                Box::new(ast),
+                Operator::Plus,
+                Box::new(ast!(Expression::Number(1))),
+            ));
+        }
+        let mut state = State::new();
+        let evaluator = Evaluator::new();
+        let result = evaluator.evaluate_expression(&mut state, &ast).unwrap();
+
+        assert_eq!(result, 50_000);
+
+        // Dropping the tree recurses as well, so it is taken apart from the top
+        while let Expression::BinaryOperator(left, _, _) = ast.node {
+            ast = *left;
+        }
+    }
+
+    #[test]
+    fn test_set_radix() {
+        let ast = ast!(Expression::Call(ast!(FunctionCall {
+            name: "set_radix".into(),
+            arguments: vec![ast!(Expression::Number(16))],
+        })));
+        let mut state = State::new();
+        let evaluator = Evaluator::new();
+        evaluator.evaluate_expression(&mut state, &ast).unwrap();
+
+        assert_eq!(state.output_radix, 16);
+    }
+
+    #[test]
+    fn test_set_radix_unsupported() {
+        let ast = ast!(Expression::Call(ast!(FunctionCall {
+            name: "set_radix".into(),
+            arguments: vec![ast!(Expression::Number(8))],
+        })));
+        let mut state = State::new();
+        let evaluator = Evaluator::new();
+        let result = evaluator.evaluate_expression(&mut state, &ast);
+        let error = state.finish(result).unwrap_err();
+
+        assert_eq!(error.message, "Unsupported radix 8, expected 2, 10 or 16");
+        assert_eq!(state.output_radix, 10);
+    }
+
+    #[test]
+    fn test_boolean_loop_terminates() {
+        let program = Parser::new(SimpleTokenizer::new(
+            "var looping = true; var i = 0; while looping { looping = false; i = i + 1; } while false { }",
+        ))
+        .parse()
+        .unwrap();
+        let mut state = State::new();
+        Evaluator::new()
+            .with_max_steps(100)
+            .evaluate_with_state(&mut state, program)
+            .unwrap();
+
+        assert_eq!(state.variable("looping"), Some(0));
+        assert_eq!(state.variable("i"), Some(1));
+    }
+
+    #[test]
+    fn test_compound_assignment() {
+        let mut state = State::new();
+        run_with_state("var x = 10; x += 5; x -= 1; x *= 2;", &mut state).unwrap();
+
+        assert_eq!(state.variable("x"), Some(28));
+
+        let error = run_with_state("y += 5;", &mut state).unwrap_err();
+
+        assert_eq!(error.message, "Variable y is not defined");
+        assert_eq!(error.span, Span { start: 0, end: 1 });
+    }
+
+    #[test]
+    fn test_assignment_expression() {
+        let mut state = State::new();
+        run_with_state(
+            "var x = 0; var y = (x = 5) + 1; var z = x = y = 2;",
+            &mut state,
+        )
+        .unwrap();
+
+        assert_eq!(state.variable("x"), Some(2));
+        assert_eq!(state.variable("y"), Some(2));
+        assert_eq!(state.variable("z"), Some(2));
+
+        let error = run_with_state("print(w = 1);", &mut state).unwrap_err();
+
+        assert_eq!(error.message, "Variable w is not defined");
+        assert_eq!(error.span, Span { start: 6, end: 11 });
+    }
+
+    #[test]
+    fn test_comparison_chain() {
+        let mut state = State::new();
+        run_with_state(
+            "var a = 1 < 2 < 3; var b = 1 < 3 < 2; var c = 3 < 2 < 1; var d = (3 < 2) < 1;",
+            &mut state,
+        )
+        .unwrap();
+
+        assert_eq!(state.variable("a"), Some(1));
+        assert_eq!(state.variable("b"), Some(0));
+        assert_eq!(state.variable("c"), Some(0));
+        assert_eq!(state.variable("d"), Some(1));
+
+        // The middle operand is evaluated once, the chain stops at the first false comparison
+        run_with_state(
+            "var n = 0; var e = 0 < (n = n + 1) < 2; var f = 1 < 0 < (n = 5);",
+            &mut state,
+        )
+        .unwrap();
+
+        assert_eq!(state.variable("n"), Some(1));
+        assert_eq!(state.variable("e"), Some(1));
+        assert_eq!(state.variable("f"), Some(0));
+    }
+
+    #[test]
+    fn test_expression_statement() {
+        let output = SharedBuffer::new();
+        let mut state = State::with_io(Cursor::new("5\n"), output.clone());
+        run_with_state(
+            "var x = 1; x + 1; input(); (x = 2) * 3; x < 2 ? print(1) : print(x);",
+            &mut state,
+        )
+        .unwrap();
+
+        assert_eq!(state.variable("x"), Some(2));
+        assert_eq!(output.contents(), "Input: \nx = 2\n");
+
+        let error = run_with_state("y + 1;", &mut State::new()).unwrap_err();
+
+        assert_eq!(error.message, "Variable y is not defined");
+        assert_eq!(error.span, Span { start: 0, end: 1 });
+    }
+
+    #[test]
+    fn test_do_while() {
+        let output = SharedBuffer::new();
+        let mut state = State::with_io(Cursor::new(""), output.clone());
+        run_with_state(
+            "var runs = 0; do { runs = runs + 1; } while (false); var i = 0; do { var j = i; i = i + 1; } while (i < 3);",
+            &mut state,
+        )
+        .unwrap();
+
+        // The body runs once even though the condition is false
+        assert_eq!(state.variable("runs"), Some(1));
+        assert_eq!(state.variable("i"), Some(3));
+        // Every iteration has its own scope
+        assert_eq!(state.variable("j"), None);
+    }
+
+    #[test]
+    fn test_match() {
+        let output = SharedBuffer::new();
+        let mut state = State::with_io(Cursor::new(""), output.clone());
+        run_with_state(
+            "
+            var i = 0;
+            while i < 4 {
+                match i * 2 {
+                    2 => { print(1); }
+                    4 => { var x = 4; print(x); }
+                    2 => { print(0); }
+                    _ => { print(i); }
+                }
+                i = i + 1;
+            }
+            ",
+            &mut state,
+        )
+        .unwrap();
+
+        // The first matching arm runs, the wildcard takes everything else
+        assert_eq!(output.contents(), "i = 0\nResult = 1\nx = 4\ni = 3\n");
+
+        // Without a wildcard an unmatched value runs nothing
+        run_with_state("var y = 0; match 5 { 1 => { y = 1; } }", &mut state).unwrap();
+        assert_eq!(state.variable("y"), Some(0));
+    }
+
+    #[test]
+    fn test_raw_identifier() {
+        let mut state = State::new();
+        run_with_state("var `while` = 1; `while` = `while` + 1;", &mut state).unwrap();
+
+        assert_eq!(state.variable("while"), Some(2));
+    }
+
+    #[test]
+    #[cfg(not(feature = "i64"))]
+    fn test_integer_boundaries() {
+        let mut state = State::new();
+        run_with_state(
+            "var min = 0 - 2147483647 - 1; var max = 2147483647; var a = min < max; var b = max < min; var c = min < min + 1;",
+            &mut state,
+        )
+        .unwrap();
+
+        assert_eq!(state.variable("min"), Some(i32::MIN));
+        assert_eq!(state.variable("a"), Some(1));
+        assert_eq!(state.variable("b"), Some(0));
+        assert_eq!(state.variable("c"), Some(1));
+
+        // Negating i32::MIN overflows
+        let error = run_with_state("var x = 0 - min;", &mut state).unwrap_err();
+
+        assert_eq!(error.message, "Integer overflow: 0 - -2147483648");
+        assert_eq!(error.span, Span { start: 8, end: 15 });
+
+        let error = run_with_state("var y = min - 1;", &mut state).unwrap_err();
+
+        assert_eq!(error.message, "Integer overflow: -2147483648 - 1");
+
+        let error = run_with_state("var z = min * (0 - 1);", &mut state).unwrap_err();
+
+        assert_eq!(error.message, "Integer overflow: -2147483648 * -1");
+    }
+
+    #[test]
+    fn test_max_steps() {
+        let program = Parser::new(SimpleTokenizer::new("var i = 0; while 1 { }"))
+            .parse()
+            .unwrap();
+        let mut state = State::new();
+        let evaluator = Evaluator::new().with_max_steps(10);
+        let error = evaluator
+            .evaluate_with_state(&mut state, program)
+            .unwrap_err();
+
+        assert_eq!(error.message, "Execution budget exceeded");
+        assert_eq!(error.span, Span { start: 11, end: 22 });
+        assert_eq!(state.steps, 11);
+    }
+
+    #[test]
+    fn test_max_steps_not_reached() {
+        let program = Parser::new(SimpleTokenizer::new(
+            "var i = 0; while i < 3 { i = i + 1; }",
+        ))
+        .parse()
+        .unwrap();
+        let mut state = State::new();
+        let evaluator = Evaluator::new().with_max_steps(8);
+        evaluator.evaluate_with_state(&mut state, program).unwrap();
+
+        assert_eq!(state.variable("i"), Some(3));
+        assert_eq!(state.steps, 8);
+    }
+
+    #[test]
+    fn test_loop_condition_evaluated_once_per_check() {
+        let output = SharedBuffer::new();
+        let mut state = State::with_io(Cursor::new("0\n1\n2\n3\n4\n"), output.clone());
+        state.register_function("count", |_, state, call| {
+            let calls = state.variable("calls").unwrap() + 1;
+            state.variables.assign("calls".into(), calls, call.span)?;
+            Ok(calls)
+        });
+
+        run_with_state(
+            "var calls = 0; var runs = 0; while input() < 3 & count() < 10 { runs = runs + 1; }",
+            &mut state,
+        )
+        .unwrap();
+
+        // Three iterations and the check that ends the loop
+        assert_eq!(output.contents().matches("Input: ").count(), 4);
+        assert_eq!(state.variable("calls"), Some(4));
+        assert_eq!(state.variable("runs"), Some(3));
+    }
+
+    #[test]
+    fn test_loop_scope() {
+        let mut state = State::new();
+        run_with_state(
+            "var i = 0; var total = 0; while i < 3 { var tmp = i * 2; total = total + tmp; i = i + 1; }",
+            &mut state,
+        )
+        .unwrap();
+
+        assert_eq!(state.variable("total"), Some(6));
+        assert_eq!(state.variable("tmp"), None);
+        assert_eq!(state.variables.depth(), 1);
+    }
+
+    #[test]
+    fn test_loop_scope_shadowing() {
+        let mut state = State::new();
+        run_with_state(
+            "var x = 1; var seen = 0; var i = 0; while i < 2 { var x = 5; seen = x; i = i + 1; }",
+            &mut state,
+        )
+        .unwrap();
+
+        assert_eq!(state.variable("x"), Some(1));
+        assert_eq!(state.variable("seen"), Some(5));
+    }
+
+    #[test]
+    fn test_error_node() {
+        let (program, _) =
+            Parser::new(SimpleTokenizer::new("var x = 1; x = ; x = 2;")).parse_partial();
+        let mut state = State::new();
+        let error = Evaluator::new()
+            .evaluate_with_state(&mut state, program)
+            .unwrap_err();
+
+        assert_eq!(error.message, "Cannot run code that failed to parse");
+        assert_eq!(error.span, Span { start: 15, end: 15 });
+        assert_eq!(state.variable("x"), Some(1));
+    }
+
+    #[test]
+    fn test_const() {
+        let mut state = State::new();
+        run_with_state("const limit = 3; var x = limit * 2;", &mut state).unwrap();
+
+        assert_eq!(state.variable("x"), Some(6));
+
+        for (source, span) in [
+            ("const limit = 3; limit = 4;", Span { start: 17, end: 27 }),
+            ("const limit = 3; limit += 1;", Span { start: 17, end: 28 }),
+            (
+                "const limit = 3; print(limit = 4);",
+                Span { start: 23, end: 32 },
+            ),
+        ] {
+            let error = run_with_state(source, &mut State::new()).unwrap_err();
+
+            assert_eq!(error.message, "Cannot reassign const limit");
+            assert_eq!(error.span, span);
+        }
+
+        for source in ["const x = 1; const x = 2;", "const x = 1; var x = 2;"] {
+            let error = run_with_state(source, &mut State::new()).unwrap_err();
+
+            assert_eq!(error.message, "Variable x is already defined");
+        }
+    }
+
+    #[test]
+    fn test_evaluate_with_callback() {
+        let mut lines = Vec::new();
+        let program = Parser::new(SimpleTokenizer::new(
+            "var i = 1; while i < 4 { print(i); i = i + 1; } write(i); write(5);",
+        ))
+        .parse()
+        .unwrap();
+
+        Evaluator::new()
+            .evaluate_with(program, &mut |line| lines.push(line.to_string()))
+            .unwrap();
+
+        // The text of `write` is passed on at the end
+        assert_eq!(lines, ["i = 1", "i = 2", "i = 3", "45"]);
+
+        // Lines printed before an error are passed on as well
+        let mut lines = Vec::new();
+        let program = Parser::new(SimpleTokenizer::new("print(1); print(2); x = y;"))
+            .parse()
+            .unwrap();
+
+        let error = Evaluator::new()
+            .evaluate_with(program, &mut |line| lines.push(line.to_string()))
+            .unwrap_err();
+
+        assert_eq!(error.message, "Variable y is not defined");
+        assert_eq!(lines, ["Result = 1", "Result = 2"]);
+    }
+
+    #[test]
+    fn test_callback_with_input() {
+        // The host picks the input by building the state itself
+        let mut lines = Vec::new();
+        let mut state = State::with_io(
+            Cursor::new("3\n"),
+            LineCallback::new(|line: &str| lines.push(line.to_string())),
+        );
+        run_with_state("var n = input(); print(n * 2);", &mut state).unwrap();
+        drop(state);
+
+        assert_eq!(lines, ["Input: ", "Result = 6"]);
+    }
+
+    #[test]
+    fn test_empty_bodies() {
+        let output = SharedBuffer::new();
+        let mut state = State::with_io(Cursor::new("1\n2\n3\n"), output.clone());
+        run_with_state(
+            "var x = 1; while 0 { } do { } while (0); match x { 1 => { } } while input() < 3 { } print(x);",
+            &mut state,
+        )
+        .unwrap();
+
+        // The loop with the false condition is skipped, the other one only reads input
+        assert_eq!(output.contents(), "Input: \nInput: \nInput: \nx = 1\n");
+    }
+
+    #[test]
+    fn test_multi_assignment() {
+        let output = SharedBuffer::new();
+        let mut state = State::with_io(Cursor::new(""), output.clone());
+        run_with_state(
+            "var a, b = 1, 2 * 3; var i = 0; while i < 1 { var a, b = b, a; print(a, b); i = 1; }",
+            &mut state,
+        )
+        .unwrap();
+
+        assert_eq!(state.variable("a"), Some(1));
+        assert_eq!(state.variable("b"), Some(6));
+        // Both values are read before the inner `a` and `b` exist
+        assert_eq!(output.contents(), "6 1\n");
+
+        let error = run_with_state("var a = 1; var b, a = 2, 3;", &mut State::new()).unwrap_err();
+        assert_eq!(error.message, "Variable a is already defined");
+        assert_eq!(error.span, Span { start: 11, end: 27 });
+    }
+
+    #[test]
+    #[cfg(feature = "i64")]
+    fn test_i64_numbers() {
+        let mut state = State::new();
+        run_with_state(
+            "var big = 3000000000; var sum = big + big; var shifted = 1 << 40; var x = 0; x = sum;",
+            &mut state,
+        )
+        .unwrap();
+
+        assert_eq!(state.variable("big"), Some(3_000_000_000));
+        assert_eq!(state.variable("x"), Some(6_000_000_000));
+        assert_eq!(state.variable("shifted"), Some(1 << 40));
+    }
+
+    #[test]
+    fn test_bitwise_operators() {
+        let mut state = State::new();
+        run_with_state(
+            "var and = 12 & 10; var or = 12 | 10; var xor = 12 ^ 10; var shl = 3 << 4; var shr = (0 - 64) >> 2; var high = 1 << 31;",
+            &mut state,
+        )
+        .unwrap();
+
+        assert_eq!(state.variable("and"), Some(8));
+        assert_eq!(state.variable("or"), Some(14));
+        assert_eq!(state.variable("xor"), Some(6));
+        assert_eq!(state.variable("shl"), Some(48));
+        assert_eq!(state.variable("shr"), Some(-16));
+        assert_eq!(state.variable("high"), Some(1 << 31));
+    }
+
+    #[test]
+    fn test_bitwise_precedence() {
+        let mut state = State::new();
+        run_with_state(
+            "var a = 1 | 2 ^ 3 & 6; var b = 1 << 2 + 1; var c = 5 & 1 < 2;",
+            &mut state,
+        )
+        .unwrap();
+
+        // 1 | (2 ^ (3 & 6))
+        assert_eq!(state.variable("a"), Some(1));
+        // 1 << (2 + 1)
+        assert_eq!(state.variable("b"), Some(8));
+        // 5 & (1 < 2)
+        assert_eq!(state.variable("c"), Some(1));
+    }
+
+    #[test]
+    fn test_left_associative() {
+        let mut state = State::new();
+        run_with_state(
+            "var a = 10 - 3 - 2; var b = 64 >> 2 >> 1; var c = 1 - 2 + 3;",
+            &mut state,
+        )
+        .unwrap();
+
+        // (10 - 3) - 2
+        assert_eq!(state.variable("a"), Some(5));
+        // (64 >> 2) >> 1
+        assert_eq!(state.variable("b"), Some(8));
+        // (1 - 2) + 3
+        assert_eq!(state.variable("c"), Some(2));
+    }
+
+    #[test]
+    fn test_shift_out_of_range() {
+        let bits = Num::BITS;
+        for (source, message) in [
+            (
+                format!("var x = 1 << {bits};"),
+                format!(
+                    "Shift amount out of range: 1 << {bits}, expected 0 to {}",
+                    bits - 1
+                ),
+            ),
+            (
+                "var x = 1 >> (0 - 1);".into(),
+                format!(
+                    "Shift amount out of range: 1 >> -1, expected 0 to {}",
+                    bits - 1
+                ),
+            ),
+        ] {
+            let error = run_with_state(&source, &mut State::new()).unwrap_err();
+
+            assert_eq!(error.message, message);
+            assert_eq!(error.span.start, 8);
+        }
+    }
+
+    #[test]
+    fn test_conditional() {
+        let mut state = State::new();
+        run_with_state(
+            "var a = 3; var b = 5; var min = a < b ? a : b; var sign = a < 0 ? 0 - 1 : a < 1 ? 0 : 1;",
+            &mut state,
+        )
+        .unwrap();
+
+        assert_eq!(state.variable("min"), Some(3));
+        assert_eq!(state.variable("sign"), Some(1));
+    }
+
+    #[test]
+    fn test_conditional_skips_untaken_branch() {
+        let mut state = State::with_io(Cursor::new("7\n8\n"), SharedBuffer::new());
+        run_with_state(
+            "var a = true ? input() : input(); var b = false ? input() : input();",
+            &mut state,
+        )
+        .unwrap();
+
+        assert_eq!(state.variable("a"), Some(7));
+        assert_eq!(state.variable("b"), Some(8));
+    }
 }
@@ -0,0 +1,531 @@
+use std::fmt;
+
+use crate::{
+    lexer::{operator_precedence, Operator, SimpleTokenizer, Token, Tokenizer},
+    parser::{AstNode, Expression, FunctionCall, Line, Parser, ParserError, Program},
+    symbol::Symbol,
+};
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum BraceStyle {
+    // `while x {`
+    SameLine,
+    // `while x` with the `{` on its own line
+    NextLine,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct FormatOptions {
+    pub brace_style: BraceStyle,
+    // Spaces per nesting level
+    pub indent_width: usize,
+    // `1 + 2` instead of `1+2`
+    pub spaces_around_operators: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            brace_style: BraceStyle::SameLine,
+            indent_width: 4,
+            spaces_around_operators: true,
+        }
+    }
+}
+
+// Canonical source of the program, parsing it again results in the same tree
+pub fn format_program(program: &AstNode<Program>) -> String {
+    format_program_with(program, &FormatOptions::default())
+}
+
+pub fn format_program_with(
+    AstNode { node: program, .. }: &AstNode<Program>,
+    options: &FormatOptions,
+) -> String {
+    let mut output = String::new();
+    format_lines(&mut output, &program.lines, 0, options);
+    output
+}
+
+// Parses and formats the source, parser errors are returned unchanged
+pub fn format_source(source: &str, options: &FormatOptions) -> Result<String, ParserError> {
+    let program = Parser::new(SimpleTokenizer::new(source)).parse()?;
+
+    Ok(format_program_with(&program, options))
+}
+
+fn format_lines(
+    output: &mut String,
+    lines: &[AstNode<Line>],
+    depth: usize,
+    options: &FormatOptions,
+) {
+    for line in lines {
+        format_line(output, line, depth, options);
+    }
+}
+
+fn format_line(
+    output: &mut String,
+    AstNode { node: line, .. }: &AstNode<Line>,
+    depth: usize,
+    options: &FormatOptions,
+) {
+    let indent = " ".repeat(depth * options.indent_width);
+
+    match line {
+        Line::Assignment(name, expression) => {
+            let name = format_name(*name);
+            let expression = format_expression_with(expression, options);
+            output.push_str(&format!("{indent}var {name} = {expression};\n"));
+        }
+        Line::MultiAssignment(names, values) => {
+            let names = names
+                .iter()
+                .map(|name| format_name(*name))
+                .collect::<Vec<String>>()
+                .join(", ");
+            let values = values
+                .iter()
+                .map(|value| format_expression_with(value, options))
+                .collect::<Vec<String>>()
+                .join(", ");
+            output.push_str(&format!("{indent}var {names} = {values};\n"));
+        }
+        Line::ConstAssignment(name, expression) => {
+            let name = format_name(*name);
+            let expression = format_expression_with(expression, options);
+            output.push_str(&format!("{indent}const {name} = {expression};\n"));
+        }
+        Line::Reassignment(name, expression) => {
+            let name = format_name(*name);
+            let expression = format_expression_with(expression, options);
+            output.push_str(&format!("{indent}{name} = {expression};\n"));
+        }
+        Line::Call(function_call) => {
+            let function_call = format_function_call(function_call, options);
+            output.push_str(&format!("{indent}{function_call};\n"));
+        }
+        Line::Expression(expression) => {
+            let expression = format_expression_with(expression, options);
+            output.push_str(&format!("{indent}{expression};\n"));
+        }
+        Line::Loop(condition, lines) => {
+            let condition = format_expression_with(condition, options);
+            match options.brace_style {
+                BraceStyle::SameLine => output.push_str(&format!("{indent}while {condition} {{\n")),
+                BraceStyle::NextLine => {
+                    output.push_str(&format!("{indent}while {condition}\n{indent}{{\n"))
+                }
+            }
+            format_lines(output, lines, depth + 1, options);
+            output.push_str(&format!("{indent}}}\n"));
+        }
+        Line::Match(scrutinee, arms) => {
+            let scrutinee = format_expression_with(scrutinee, options);
+            let arm_indent = " ".repeat((depth + 1) * options.indent_width);
+            match options.brace_style {
+                BraceStyle::SameLine => output.push_str(&format!("{indent}match {scrutinee} {{\n")),
+                BraceStyle::NextLine => {
+                    output.push_str(&format!("{indent}match {scrutinee}\n{indent}{{\n"))
+                }
+            }
+            for (pattern, lines) in arms {
+                match options.brace_style {
+                    BraceStyle::SameLine => {
+                        output.push_str(&format!("{arm_indent}{pattern} => {{\n"))
+                    }
+                    BraceStyle::NextLine => {
+                        output.push_str(&format!("{arm_indent}{pattern} =>\n{arm_indent}{{\n"))
+                    }
+                }
+                format_lines(output, lines, depth + 2, options);
+                output.push_str(&format!("{arm_indent}}}\n"));
+            }
+            output.push_str(&format!("{indent}}}\n"));
+        }
+        Line::DoWhile(lines, condition) => {
+            match options.brace_style {
+                BraceStyle::SameLine => output.push_str(&format!("{indent}do {{\n")),
+                BraceStyle::NextLine => output.push_str(&format!("{indent}do\n{indent}{{\n")),
+            }
+            format_lines(output, lines, depth + 1, options);
+            let condition = format_expression_with(condition, options);
+            output.push_str(&format!("{indent}}} while ({condition});\n"));
+        }
+        Line::Include(path) => output.push_str(&format!("{indent}include \"{path}\";\n")),
+        Line::Error => output.push_str(&format!("{indent}<error>;\n")),
+    }
+}
+
+pub fn format_expression(expression: &AstNode<Expression>) -> String {
+    format_expression_with(expression, &FormatOptions::default())
+}
+
+fn format_expression_with(
+    AstNode {
+        node: expression, ..
+    }: &AstNode<Expression>,
+    options: &FormatOptions,
+) -> String {
+    match expression {
+        Expression::Number(value) => value.to_string(),
+        Expression::Bool(value) => value.to_string(),
+        Expression::Error => "<error>".into(),
+        Expression::Identifier(name) => format_name(*name),
+        Expression::Call(function_call) => format_function_call(function_call, options),
+        Expression::Assign(name, value) => {
+            format!(
+                "{} = {}",
+                format_name(*name),
+                format_expression_with(value, options)
+            )
+        }
+        Expression::Conditional(condition, then, otherwise) => {
+            let condition_text = format_expression_with(condition, options);
+            let condition_text = match condition.node {
+                // These would take the `?` as their own
+                Expression::Assign(..) | Expression::Conditional(..) => {
+                    format!("({condition_text})")
+                }
+                _ => condition_text,
+            };
+            format!(
+                "{condition_text} ? {} : {}",
+                format_expression_with(then, options),
+                format_expression_with(otherwise, options)
+            )
+        }
+        Expression::BinaryOperator(left, operator, right) => {
+            let precedence = operator_precedence(operator);
+            let space = if options.spaces_around_operators {
+                " "
+            } else {
+                ""
+            };
+            format!(
+                "{}{space}{}{space}{}",
+                format_operand(left, precedence, false, options),
+                operator.symbol(),
+                format_operand(right, precedence, true, options)
+            )
+        }
+        Expression::Comparison(first, comparisons) => {
+            let space = if options.spaces_around_operators {
+                " "
+            } else {
+                ""
+            };
+            let mut formatted = format_operand(first, comparison_precedence(), false, options);
+            for (operator, operand) in comparisons {
+                formatted.push_str(&format!(
+                    "{space}{}{space}{}",
+                    operator.symbol(),
+                    format_operand(operand, comparison_precedence(), true, options)
+                ));
+            }
+            formatted
+        }
+    }
+}
+
+// Parentheses are needed when the operand binds looser than the operator,
+// or equally on the right side, since operators are left associative
+fn format_operand(
+    operand: &AstNode<Expression>,
+    precedence: u8,
+    right: bool,
+    options: &FormatOptions,
+) -> String {
+    let formatted = format_expression_with(operand, options);
+
+    match operand.node {
+        Expression::BinaryOperator(_, ref operator, _) => {
+            let operand_precedence = operator_precedence(operator);
+            // `(a < b) < c` without parentheses would be a comparison chain
+            let chained = operator.is_comparison() && operand_precedence == precedence;
+            if operand_precedence < precedence
+                || (right && operand_precedence == precedence)
+                || chained
+            {
+                format!("({formatted})")
+            } else {
+                formatted
+            }
+        }
+        Expression::Comparison(..) if comparison_precedence() <= precedence => {
+            format!("({formatted})")
+        }
+        // Assignment and the conditional bind looser than every operator
+        Expression::Assign(..) | Expression::Conditional(..) => format!("({formatted})"),
+        _ => formatted,
+    }
+}
+
+fn comparison_precedence() -> u8 {
+    operator_precedence(&Operator::LessThan)
+}
+
+fn format_function_call(
+    AstNode {
+        node: function_call,
+        ..
+    }: &AstNode<FunctionCall>,
+    options: &FormatOptions,
+) -> String {
+    let arguments = function_call
+        .arguments
+        .iter()
+        .map(|argument| format_expression_with(argument, options))
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    format!("{}({arguments})", format_name(function_call.name))
+}
+
+// Names that would not read back as a plain identifier, like keywords, are written as raw
+// identifiers
+fn format_name(name: Symbol) -> String {
+    match SimpleTokenizer::new(name.as_str()).collect_tokens() {
+        Ok(tokens) if tokens == [Token::Identifier(name), Token::Eof] => name.to_string(),
+        _ => format!("`{name}`"),
+    }
+}
+
+// `println!("{program}")` prints the canonical source, like `format_program`
+impl fmt::Display for AstNode<Program> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(&format_program(self))
+    }
+}
+
+// Without the line break, a loop still spans several lines
+impl fmt::Display for AstNode<Line> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let mut output = String::new();
+        format_line(&mut output, self, 0, &FormatOptions::default());
+        formatter.write_str(output.trim_end_matches('\n'))
+    }
+}
+
+impl fmt::Display for AstNode<Expression> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(&format_expression(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        lexer::SimpleTokenizer,
+        parser::{AstNode, Line, Parser, Program},
+    };
+
+    use super::{format_program, format_source, BraceStyle, FormatOptions};
+
+    fn parse(source: &str) -> AstNode<Program> {
+        Parser::new(SimpleTokenizer::new(source)).parse().unwrap()
+    }
+
+    #[test]
+    fn format_canonical_program() {
+        let source = "\
+var n = input();
+var i = 0;
+while i < n {
+    print(i, n * (i + 1));
+    while 0 {
+    }
+    i = i + 1;
+}
+";
+
+        assert_eq!(format_program(&parse(source)), source);
+    }
+
+    #[test]
+    fn display() {
+        let program = parse("var x=1+2*(3+4);while x<3{x=x+1;}");
+
+        assert_eq!(
+            program.to_string(),
+            "var x = 1 + 2 * (3 + 4);\nwhile x < 3 {\n    x = x + 1;\n}\n"
+        );
+        assert_eq!(
+            program.node.lines[0].to_string(),
+            "var x = 1 + 2 * (3 + 4);"
+        );
+        assert_eq!(
+            program.node.lines[1].to_string(),
+            "while x < 3 {\n    x = x + 1;\n}"
+        );
+
+        let Line::Assignment(_, expression) = &program.node.lines[0].node else {
+            panic!("expected an assignment");
+        };
+        assert_eq!(expression.to_string(), "1 + 2 * (3 + 4)");
+    }
+
+    #[test]
+    fn format_normalizes_whitespace() {
+        let source = "var   x=1+2 ;while x<3{x=x+1;print( x );}";
+        let expected = "\
+var x = 1 + 2;
+while x < 3 {
+    x = x + 1;
+    print(x);
+}
+";
+
+        assert_eq!(format_program(&parse(source)), expected);
+    }
+
+    #[test]
+    fn format_parentheses_by_precedence() {
+        let source = "var a = (1 + 2) * 3; var b = 1 + 2 * 3; var c = (1 - 2) - (3 - 4); var d = ((1 * 2)) + 3;";
+        let expected = "\
+var a = (1 + 2) * 3;
+var b = 1 + 2 * 3;
+var c = 1 - 2 - (3 - 4);
+var d = 1 * 2 + 3;
+";
+
+        assert_eq!(format_program(&parse(source)), expected);
+    }
+
+    #[test]
+    fn format_raw_identifiers() {
+        let source = "var `while` = `x` + 1;";
+
+        assert_eq!(format_program(&parse(source)), "var `while` = x + 1;\n");
+    }
+
+    #[test]
+    fn format_assignment_expression() {
+        let source = "var y = (x = 5) + 1; print(x = y = 2);";
+        let expected = "var y = (x = 5) + 1;\nprint(x = y = 2);\n";
+
+        assert_eq!(format_program(&parse(source)), expected);
+    }
+
+    #[test]
+    fn format_conditional() {
+        let source = "var y = (a ? b : c) ? d : e?f:g; print((x < 1 ? 1 : 2) * 3);";
+        let expected = "var y = (a ? b : c) ? d : e ? f : g;\nprint((x < 1 ? 1 : 2) * 3);\n";
+
+        assert_eq!(format_program(&parse(source)), expected);
+    }
+
+    #[test]
+    fn format_match() {
+        let source = "match x{1=>{print(x);}_=>{}}";
+        let expected = "\
+match x {
+    1 => {
+        print(x);
+    }
+    _ => {
+    }
+}
+";
+
+        assert_eq!(format_program(&parse(source)), expected);
+    }
+
+    #[test]
+    fn format_do_while() {
+        let source = "do{x=x+1;}while(x<3);";
+        let expected = "do {\n    x = x + 1;\n} while (x < 3);\n";
+
+        assert_eq!(format_program(&parse(source)), expected);
+    }
+
+    #[test]
+    fn format_multi_assignment() {
+        let source = "var a,b=1,2+3;";
+        let expected = "var a, b = 1, 2 + 3;\n";
+
+        assert_eq!(format_program(&parse(source)), expected);
+    }
+
+    #[test]
+    fn format_expression_statement() {
+        let source = "x+1; (x=2)*3; input();";
+        let expected = "x + 1;\n(x = 2) * 3;\ninput();\n";
+
+        assert_eq!(format_program(&parse(source)), expected);
+    }
+
+    #[test]
+    fn format_comparison_chain() {
+        let source =
+            "var a = 1<x+1<3; var b = (1 < x) < 3; var c = 1 < (x < 3); var d = (1 < x < 3) & 1;";
+        let expected = "\
+var a = 1 < x + 1 < 3;
+var b = (1 < x) < 3;
+var c = 1 < (x < 3);
+var d = 1 < x < 3 & 1;
+";
+
+        assert_eq!(format_program(&parse(source)), expected);
+    }
+
+    #[test]
+    fn format_is_idempotent() {
+        let source = "var x=(1<2)<3; while (x) { x = x-(1-0)*(2+3 * x); print(x); print(); }";
+        let formatted = format_program(&parse(source));
+        let reparsed = parse(&formatted);
+
+        assert_eq!(reparsed, parse(&format_program(&reparsed)));
+        assert_eq!(format_program(&reparsed), formatted);
+    }
+
+    #[test]
+    fn format_brace_styles() {
+        let source = "var x = 0; while x < 3 { x = x + 1; while 0 { } }";
+        let next_line = FormatOptions {
+            brace_style: BraceStyle::NextLine,
+            ..FormatOptions::default()
+        };
+
+        assert_eq!(
+            format_source(source, &FormatOptions::default()).unwrap(),
+            "\
+var x = 0;
+while x < 3 {
+    x = x + 1;
+    while 0 {
+    }
+}
+"
+        );
+        assert_eq!(
+            format_source(source, &next_line).unwrap(),
+            "\
+var x = 0;
+while x < 3
+{
+    x = x + 1;
+    while 0
+    {
+    }
+}
+"
+        );
+    }
+
+    #[test]
+    fn format_compact_options() {
+        let options = FormatOptions {
+            indent_width: 2,
+            spaces_around_operators: false,
+            ..FormatOptions::default()
+        };
+
+        assert_eq!(
+            format_source("while x < 3 { x = (x + 1) * 2; }", &options).unwrap(),
+            "while x<3 {\n  x = (x+1)*2;\n}\n"
+        );
+        assert!(format_source("while {", &options).is_err());
+    }
+}
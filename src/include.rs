@@ -0,0 +1,181 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    lexer::SimpleTokenizer,
+    parser::{AstNode, Line, Parser, Program},
+    source_map::SourceMap,
+    utils::{Span, SpanError},
+};
+
+// Loads a program and the files it includes. Every file is added to the source map and parsed
+// at its offset there, so spans of included lines still point into the right file.
+#[derive(Default)]
+pub struct Loader {
+    source_map: SourceMap,
+    optional_semicolons: bool,
+    // Parse with `Parser::parse_partial` and collect the errors instead of stopping at the first
+    recover: bool,
+    errors: Vec<SpanError>,
+    // Files being loaded, the last one is the innermost include
+    stack: Vec<PathBuf>,
+}
+
+impl Loader {
+    pub fn new() -> Loader {
+        Loader::default()
+    }
+
+    pub fn with_optional_semicolons(mut self) -> Self {
+        self.optional_semicolons = true;
+        self
+    }
+
+    pub fn with_error_recovery(mut self) -> Self {
+        self.recover = true;
+        self
+    }
+
+    // Parser errors collected with error recovery, in the order they were found
+    pub fn take_errors(&mut self) -> Vec<SpanError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    pub fn source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
+    pub fn load(&mut self, path: &Path) -> Result<AstNode<Program>, SpanError> {
+        let text = fs::read_to_string(path).map_err(|err| SpanError {
+            message: format!("Can not read file {}: {err}", path.display()),
+            span: Span {
+                start: self.source_map.text().len(),
+                end: self.source_map.text().len(),
+            },
+        })?;
+
+        self.load_file(path, &text)
+    }
+
+    fn load_file(&mut self, path: &Path, text: &str) -> Result<AstNode<Program>, SpanError> {
+        // Canonical paths, so `lib.txt` and `./lib.txt` are the same file for the cycle check
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let directory = canonical
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        self.stack.push(canonical);
+        let result = self.load_source(&path.display().to_string(), text, &directory);
+        self.stack.pop();
+
+        result
+    }
+
+    // A program that is not read from a file, like stdin. Includes are resolved against
+    // `directory`.
+    pub fn load_source(
+        &mut self,
+        name: &str,
+        text: &str,
+        directory: &Path,
+    ) -> Result<AstNode<Program>, SpanError> {
+        let offset = self.source_map.add(name, text);
+
+        let tokenizer = SimpleTokenizer::new(text).with_offset(offset);
+        let mut parser = if self.optional_semicolons {
+            Parser::new(tokenizer.with_newlines()).with_optional_semicolons()
+        } else {
+            Parser::new(tokenizer)
+        };
+        let mut program = if self.recover {
+            let (program, errors) = parser.parse_partial();
+            self.errors.extend(errors);
+            program
+        } else {
+            parser.parse()?
+        };
+
+        program.node.lines = self.resolve(program.node.lines, directory)?;
+
+        Ok(program)
+    }
+
+    // Replaces includes with the lines of the included files, also inside loops
+    fn resolve(
+        &mut self,
+        lines: Vec<AstNode<Line>>,
+        directory: &Path,
+    ) -> Result<Vec<AstNode<Line>>, SpanError> {
+        let mut resolved = Vec::with_capacity(lines.len());
+
+        for line in lines {
+            match line.node {
+                Line::Include(path) => {
+                    let path = directory.join(path.as_str());
+                    let included = self.include(&path, line.span)?;
+                    resolved.extend(included.node.lines);
+                }
+                Line::Loop(condition, body) => resolved.push(AstNode {
+                    node: Line::Loop(condition, self.resolve(body, directory)?),
+                    span: line.span,
+                }),
+                Line::Match(scrutinee, arms) => {
+                    let arms = arms
+                        .into_iter()
+                        .map(|(pattern, body)| Ok((pattern, self.resolve(body, directory)?)))
+                        .collect::<Result<_, SpanError>>()?;
+                    resolved.push(AstNode {
+                        node: Line::Match(scrutinee, arms),
+                        span: line.span,
+                    })
+                }
+                Line::DoWhile(body, condition) => resolved.push(AstNode {
+                    node: Line::DoWhile(self.resolve(body, directory)?, condition),
+                    span: line.span,
+                }),
+                node => resolved.push(AstNode {
+                    node,
+                    span: line.span,
+                }),
+            }
+        }
+
+        Ok(resolved)
+    }
+
+    fn include(&mut self, path: &Path, span: Span) -> Result<AstNode<Program>, SpanError> {
+        let canonical = path.canonicalize().map_err(|err| SpanError {
+            message: format!("Can not read file {}: {err}", path.display()),
+            span,
+        })?;
+
+        if let Some(index) = self.stack.iter().position(|file| *file == canonical) {
+            let cycle = self.stack[index..]
+                .iter()
+                .chain([&canonical])
+                .map(|file| file_name(file))
+                .collect::<Vec<String>>()
+                .join(" -> ");
+            return Err(SpanError {
+                message: format!("Include cycle: {cycle}"),
+                span,
+            });
+        }
+
+        let text = fs::read_to_string(&canonical).map_err(|err| SpanError {
+            message: format!("Can not read file {}: {err}", path.display()),
+            span,
+        })?;
+
+        self.load_file(path, &text)
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
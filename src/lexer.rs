@@ -1,28 +1,71 @@
-use crate::utils::{Span, SpanError};
+use crate::{
+    symbol::Symbol,
+    utils::{Num, Span, SpanError},
+};
 use lazy_regex::regex;
 use regex::{Captures, Regex};
+use serde::Serialize;
+use std::fmt;
 use std::str;
-use substring::Substring;
 
 pub type TokenizerError = SpanError;
 
-#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize)]
 pub enum Operator {
     Plus,
     Minus,
     Multiplication,
     LessThan,
+    BitwiseAnd,
+    BitwiseOr,
+    BitwiseXor,
+    ShiftLeft,
+    ShiftRight,
+}
+
+impl Operator {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Operator::Plus => "+",
+            Operator::Minus => "-",
+            Operator::Multiplication => "*",
+            Operator::LessThan => "<",
+            Operator::BitwiseAnd => "&",
+            Operator::BitwiseOr => "|",
+            Operator::BitwiseXor => "^",
+            Operator::ShiftLeft => "<<",
+            Operator::ShiftRight => ">>",
+        }
+    }
+
+    // Comparisons chain, `a < b < c` is `a < b` and `b < c`
+    pub fn is_comparison(&self) -> bool {
+        matches!(self, Operator::LessThan)
+    }
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(self.symbol())
+    }
 }
 
+// Follows C: `&`, `^` and `|` bind looser than comparisons, so `x & 1 < y` is `x & (1 < y)`,
+// and shifts sit between comparisons and addition
 pub fn operator_precedence(op: &Operator) -> u8 {
     match op {
-        Operator::LessThan => 1,
-        Operator::Plus | Operator::Minus => 2,
-        Operator::Multiplication => 3,
+        Operator::BitwiseOr => 1,
+        Operator::BitwiseXor => 2,
+        Operator::BitwiseAnd => 3,
+        Operator::LessThan => 4,
+        Operator::ShiftLeft | Operator::ShiftRight => 5,
+        Operator::Plus | Operator::Minus => 6,
+        Operator::Multiplication => 7,
     }
 }
 
-#[derive(PartialEq, Eq, Debug, Clone)]
+// Identifiers are interned, so tokens are cheap to copy when peeking
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub enum Token {
     OpeningParenthesis,
     ClosingParenthesis,
@@ -30,16 +73,68 @@ pub enum Token {
     ClosingCurlyBracket,
     SemiColon,
     Equals,
-    Number(i32),
-    Identifier(String),
+    // `+=`, `-=` and `*=`
+    CompoundAssignment(Operator),
+    Number(Num),
+    Identifier(Symbol),
+    // `"lib.txt"`, only used by `include`
+    StringLiteral(Symbol),
     Operator(Operator),
     Var,
+    Const,
     While,
+    Do,
+    Match,
+    Include,
+    True,
+    False,
     Comma,
+    Question,
+    Colon,
+    // `=>` between a match pattern and its block
+    FatArrow,
+    // The wildcard pattern of a match
+    Underscore,
+    // Only emitted when enabled by `SimpleTokenizer::with_newlines`
+    Newline,
     Eof,
 }
 
-#[derive(PartialEq, Eq, Debug, Clone)]
+// The source text of the token, for error messages
+impl fmt::Display for Token {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::OpeningParenthesis => formatter.write_str("("),
+            Token::ClosingParenthesis => formatter.write_str(")"),
+            Token::OpeningCurlyBracket => formatter.write_str("{"),
+            Token::ClosingCurlyBracket => formatter.write_str("}"),
+            Token::SemiColon => formatter.write_str(";"),
+            Token::Equals => formatter.write_str("="),
+            Token::CompoundAssignment(operator) => write!(formatter, "{operator}="),
+            Token::Number(value) => write!(formatter, "{value}"),
+            Token::Identifier(name) => write!(formatter, "{name}"),
+            Token::StringLiteral(value) => write!(formatter, "\"{value}\""),
+            Token::Operator(operator) => write!(formatter, "{operator}"),
+            Token::Var => formatter.write_str("var"),
+            Token::Const => formatter.write_str("const"),
+            Token::While => formatter.write_str("while"),
+            Token::Do => formatter.write_str("do"),
+            Token::Match => formatter.write_str("match"),
+            Token::Include => formatter.write_str("include"),
+            Token::True => formatter.write_str("true"),
+            Token::False => formatter.write_str("false"),
+            Token::Comma => formatter.write_str(","),
+            Token::Question => formatter.write_str("?"),
+            Token::Colon => formatter.write_str(":"),
+            Token::FatArrow => formatter.write_str("=>"),
+            Token::Underscore => formatter.write_str("_"),
+            Token::Newline => formatter.write_str("line break"),
+            Token::Eof => formatter.write_str("end of input"),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub struct TokenNode {
     pub token: Token,
     pub span: Span,
@@ -67,6 +162,7 @@ pub trait Tokenizer {
     fn peek(&mut self) -> TokenResult;
     fn get_empty_span(&mut self) -> Result<Span, TokenizerError>;
     fn collect_tokens(&mut self) -> Result<Vec<Token>, TokenizerError>;
+    fn collect_spanned_tokens(&mut self) -> Result<Vec<TokenNode>, TokenizerError>;
 }
 
 pub struct SimpleTokenizer<'a> {
@@ -76,48 +172,100 @@ pub struct SimpleTokenizer<'a> {
     rules: Vec<TokenizerRule>,
     matches_keyword: Regex,
     terminated: bool,
+    // Set once the iterator returned Eof or an error
+    exhausted: bool,
+    newlines: bool,
+    // Whether the last token can end a statement, so a newline after it is significant
+    ends_statement: bool,
+    // Newlines inside parentheses never end a statement
+    parenthesis_depth: usize,
+    // Added to every span, see `with_offset`
+    offset: usize,
 }
 
 pub enum TokenizerRule {
     Char(char, Token),
     String(&'static str, Token),
-    Regex(Regex, fn(&Captures) -> Token),
+    // The factory returns the message of the error for matches that are not valid tokens
+    Regex(Regex, fn(&Captures) -> Result<Token, String>),
+}
+
+// The rules of the language, in the order they are tried. The first rule that matches wins,
+// so longer operators come before their prefixes.
+pub fn default_rules() -> Vec<TokenizerRule> {
+    vec![
+        // Before the single character operators, so `+=` is not read as `+` and `=`
+        TokenizerRule::String("+=", Token::CompoundAssignment(Operator::Plus)),
+        TokenizerRule::String("-=", Token::CompoundAssignment(Operator::Minus)),
+        TokenizerRule::String("*=", Token::CompoundAssignment(Operator::Multiplication)),
+        TokenizerRule::String("=>", Token::FatArrow),
+        // Before `<`
+        TokenizerRule::String("<<", Token::Operator(Operator::ShiftLeft)),
+        TokenizerRule::String(">>", Token::Operator(Operator::ShiftRight)),
+        TokenizerRule::Char('(', Token::OpeningParenthesis),
+        TokenizerRule::Char(')', Token::ClosingParenthesis),
+        TokenizerRule::Char('{', Token::OpeningCurlyBracket),
+        TokenizerRule::Char('}', Token::ClosingCurlyBracket),
+        TokenizerRule::Char(';', Token::SemiColon),
+        TokenizerRule::Char('=', Token::Equals),
+        TokenizerRule::Char('+', Token::Operator(Operator::Plus)),
+        TokenizerRule::Char('-', Token::Operator(Operator::Minus)),
+        TokenizerRule::Char('*', Token::Operator(Operator::Multiplication)),
+        TokenizerRule::Char('<', Token::Operator(Operator::LessThan)),
+        TokenizerRule::Char('&', Token::Operator(Operator::BitwiseAnd)),
+        TokenizerRule::Char('|', Token::Operator(Operator::BitwiseOr)),
+        TokenizerRule::Char('^', Token::Operator(Operator::BitwiseXor)),
+        TokenizerRule::Char(',', Token::Comma),
+        TokenizerRule::Char('?', Token::Question),
+        TokenizerRule::Char(':', Token::Colon),
+        TokenizerRule::Char('_', Token::Underscore),
+        TokenizerRule::Regex(
+            Regex::new(r"^([a-zA-Z][a-zA-Z0-9_]*)").unwrap(),
+            |cap: &Captures| Ok(Token::Identifier(Symbol::intern(&cap[0]))),
+        ),
+        // Raw identifiers like `while` can use the name of a keyword
+        TokenizerRule::Regex(
+            Regex::new(r"^`([a-zA-Z][a-zA-Z0-9_]*)`").unwrap(),
+            |cap: &Captures| Ok(Token::Identifier(Symbol::intern(&cap[1]))),
+        ),
+        TokenizerRule::String("var", Token::Var),
+        TokenizerRule::String("const", Token::Const),
+        TokenizerRule::String("while", Token::While),
+        TokenizerRule::String("do", Token::Do),
+        TokenizerRule::String("match", Token::Match),
+        TokenizerRule::String("include", Token::Include),
+        TokenizerRule::String("true", Token::True),
+        TokenizerRule::String("false", Token::False),
+        TokenizerRule::Regex(Regex::new(r#"^"([^"\n]*)""#).unwrap(), |cap: &Captures| {
+            Ok(Token::StringLiteral(Symbol::intern(&cap[1])))
+        }),
+        TokenizerRule::Regex(Regex::new(r"^(\d+)").unwrap(), |cap: &Captures| {
+            // The digits only fail to parse if the number does not fit
+            cap[0]
+                .parse()
+                .map(Token::Number)
+                .map_err(|_| "Number literal too large".to_string())
+        }),
+    ]
 }
 
 impl SimpleTokenizer<'_> {
-    pub fn new(data: &str) -> SimpleTokenizer {
-        let rules = vec![
-            TokenizerRule::Char('(', Token::OpeningParenthesis),
-            TokenizerRule::Char(')', Token::ClosingParenthesis),
-            TokenizerRule::Char('{', Token::OpeningCurlyBracket),
-            TokenizerRule::Char('}', Token::ClosingCurlyBracket),
-            TokenizerRule::Char(';', Token::SemiColon),
-            TokenizerRule::Char('=', Token::Equals),
-            TokenizerRule::Char('+', Token::Operator(Operator::Plus)),
-            TokenizerRule::Char('-', Token::Operator(Operator::Minus)),
-            TokenizerRule::Char('*', Token::Operator(Operator::Multiplication)),
-            TokenizerRule::Char('<', Token::Operator(Operator::LessThan)),
-            TokenizerRule::Char(',', Token::Comma),
-            TokenizerRule::Regex(
-                Regex::new(r"^([a-zA-Z][a-zA-Z0-9_]*)").unwrap(),
-                |cap: &Captures| Token::Identifier(cap[0].to_string()),
-            ),
-            TokenizerRule::String("var", Token::Var),
-            TokenizerRule::String("while", Token::While),
-            TokenizerRule::Regex(Regex::new(r"^(\d+)").unwrap(), |cap: &Captures| {
-                Token::Number(cap[0].parse().unwrap())
-            }),
-        ];
+    pub fn new(data: &str) -> SimpleTokenizer<'_> {
+        SimpleTokenizer::with_rules(data, default_rules())
+    }
 
+    // A tokenizer for a variant of the language, like `default_rules` with an extra keyword.
+    // Every `String` rule is a keyword the identifier rule does not match.
+    pub fn with_rules(data: &str, rules: Vec<TokenizerRule>) -> SimpleTokenizer<'_> {
         // Do not match keywords as identifiers
         let matches_keyword = {
             let string_rules = rules
                 .iter()
                 .filter_map(|rule| match rule {
-                    TokenizerRule::String(str, _) => Some(*str),
+                    TokenizerRule::String(str, _) => Some(regex::escape(str)),
                     _ => None,
                 })
-                .collect::<Vec<&str>>()
+                .collect::<Vec<String>>()
                 .join("|");
             let string_rules_re = format!("^({string_rules})$");
 
@@ -131,14 +279,50 @@ impl SimpleTokenizer<'_> {
             rules,
             matches_keyword,
             terminated: false,
+            exhausted: false,
+            newlines: false,
+            ends_statement: false,
+            parenthesis_depth: 0,
+            offset: 0,
         }
     }
 
-    fn read(&self, start_index: usize) -> TokenResult {
-        let view = self.data.substring(start_index, self.data.len());
+    // Emits a Newline token for line breaks after an identifier, a number or `)`
+    // outside of parentheses, so they can terminate statements
+    pub fn with_newlines(mut self) -> Self {
+        self.newlines = true;
+        self
+    }
+
+    // Shifts the spans by `offset`, for sources that are part of a `SourceMap`
+    pub fn with_offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    fn read(&self, mut start_index: usize) -> TokenResult {
+        // Spans are byte offsets, slicing by bytes keeps reading a token independent of its position
+        let mut view = &self.data[start_index..];
 
         let whitespace_re = regex!(r"^(\s+)");
 
+        while let Some(cap) = whitespace_re.captures(view) {
+            if let Some(offset) = cap[0]
+                .find('\n')
+                .filter(|_| self.newlines && self.ends_statement && self.parenthesis_depth == 0)
+            {
+                let newline_index = start_index + offset;
+                return Ok(TokenNode::new(
+                    Token::Newline,
+                    newline_index,
+                    newline_index + 1,
+                ));
+            }
+
+            start_index += cap[0].len();
+            view = &self.data[start_index..];
+        }
+
         if view.is_empty() {
             if self.terminated {
                 return Err(TokenizerError::new(
@@ -147,42 +331,58 @@ impl SimpleTokenizer<'_> {
                     start_index,
                 ));
             } else {
-                return Ok(TokenNode::new(Token::Eof, start_index, start_index + 1));
+                return Ok(TokenNode::new(Token::Eof, start_index, start_index));
             }
-        } else if let Some(cap) = whitespace_re.captures(view) {
-            return self.read(start_index + cap[0].len());
         }
 
         for rule in &self.rules {
             match rule {
                 TokenizerRule::Char(ch, token) => {
                     if view.starts_with(*ch) {
-                        return Ok(TokenNode::new(token.clone(), start_index, start_index + 1));
+                        return Ok(TokenNode::new(*token, start_index, start_index + 1));
                     }
                 }
                 TokenizerRule::String(str, token) => {
                     if view.starts_with(str) {
-                        return Ok(TokenNode::new(
-                            token.clone(),
-                            start_index,
-                            start_index + str.len(),
-                        ));
+                        return Ok(TokenNode::new(*token, start_index, start_index + str.len()));
                     }
                 }
                 TokenizerRule::Regex(regex, factory) => {
                     if let Some(cap) = regex.captures(view) {
                         if !self.matches_keyword.is_match(&cap[0]) {
-                            return Ok(TokenNode::new(
-                                factory(&cap),
-                                start_index,
-                                start_index + cap[0].len(),
-                            ));
+                            let end_index = start_index + cap[0].len();
+                            return match factory(&cap) {
+                                Ok(token) => Ok(TokenNode::new(token, start_index, end_index)),
+                                Err(message) => {
+                                    Err(TokenizerError::new(message, start_index, end_index))
+                                }
+                            };
                         }
                     }
                 }
             }
         }
 
+        if view.starts_with('"') {
+            return Err(TokenizerError::new(
+                "Unterminated string".into(),
+                start_index,
+                start_index + view.len(),
+            ));
+        }
+
+        if let Some(rest) = view.strip_prefix('`') {
+            let message = match rest.find('`') {
+                Some(_) => "Invalid raw identifier",
+                None => "Unterminated raw identifier",
+            };
+            return Err(TokenizerError::new(
+                message.into(),
+                start_index,
+                start_index + view.len(),
+            ));
+        }
+
         Err(TokenizerError::new(
             "Unexpected token!".into(),
             start_index,
@@ -191,17 +391,38 @@ impl SimpleTokenizer<'_> {
     }
 
     fn advance(&mut self) -> TokenResult {
-        let result = self.read(self.cursor);
+        let mut result = self.read(self.cursor);
 
-        self.cursor = match result {
-            Ok(ref source) => source.span.end,
-            Err(ref source) => source.span.end,
+        let span = match result {
+            Ok(ref mut source) => &mut source.span,
+            Err(ref mut source) => &mut source.span,
         };
+        self.cursor = span.end;
+        span.start += self.offset;
+        span.end += self.offset;
 
         if let Ok(TokenNode { ref token, .. }) = result {
             if *token == Token::Eof && self.cursor == self.data.len() {
                 self.terminated = true;
             }
+
+            match token {
+                Token::OpeningParenthesis => self.parenthesis_depth += 1,
+                Token::ClosingParenthesis => {
+                    self.parenthesis_depth = self.parenthesis_depth.saturating_sub(1)
+                }
+                _ => {}
+            }
+
+            self.ends_statement = matches!(
+                token,
+                Token::Identifier(_)
+                    | Token::Number(_)
+                    | Token::StringLiteral(_)
+                    | Token::True
+                    | Token::False
+                    | Token::ClosingParenthesis
+            );
         }
 
         result
@@ -230,13 +451,21 @@ impl Tokenizer for SimpleTokenizer<'_> {
     }
 
     fn collect_tokens(&mut self) -> Result<Vec<Token>, TokenizerError> {
+        Ok(self
+            .collect_spanned_tokens()?
+            .into_iter()
+            .map(|token_node| token_node.token)
+            .collect())
+    }
+
+    fn collect_spanned_tokens(&mut self) -> Result<Vec<TokenNode>, TokenizerError> {
         let mut result = Vec::new();
 
         let mut eof = false;
         while !eof {
-            let next = self.next()?;
+            let next = Tokenizer::next(self)?;
             eof = next.token == Token::Eof;
-            result.push(next.token);
+            result.push(next);
         }
 
         Ok(result)
@@ -249,9 +478,46 @@ impl Tokenizer for SimpleTokenizer<'_> {
     }
 }
 
+// Every token of the source with its span, up to and including Eof, for tools like syntax
+// highlighting
+pub fn tokenize(source: &str) -> Result<Vec<TokenNode>, TokenizerError> {
+    SimpleTokenizer::new(source).collect_spanned_tokens()
+}
+
+// One token per line with its span, like `0..3 Var`, used by `--tokens`
+pub fn dump_tokens(source: &str) -> Result<String, TokenizerError> {
+    Ok(tokenize(source)?
+        .iter()
+        .map(|TokenNode { token, span }| format!("{}..{} {token:?}\n", span.start, span.end))
+        .collect())
+}
+
+// Yields the tokens lazily up to and including the first Eof or error
+impl Iterator for SimpleTokenizer<'_> {
+    type Item = TokenResult;
+
+    fn next(&mut self) -> Option<TokenResult> {
+        if self.exhausted {
+            return None;
+        }
+
+        let result = Tokenizer::next(self);
+        self.exhausted = !matches!(result, Ok(ref node) if node.token != Token::Eof);
+
+        Some(result)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::lexer::{SimpleTokenizer, Token, Tokenizer};
+    use crate::{
+        lexer::{
+            default_rules, dump_tokens, tokenize, Operator, SimpleTokenizer, Token, TokenNode,
+            Tokenizer, TokenizerError, TokenizerRule,
+        },
+        parser::Parser,
+        utils::Span,
+    };
 
     #[test]
     fn empty() {
@@ -273,13 +539,399 @@ mod tests {
         );
     }
 
+    #[test]
+    fn shifts_and_comparison() {
+        let mut tokenizer = SimpleTokenizer::new("a << 1 < b >> 2 & 3");
+        assert_eq!(
+            tokenizer.collect_tokens().unwrap(),
+            [
+                Token::Identifier("a".into()),
+                Token::Operator(Operator::ShiftLeft),
+                Token::Number(1),
+                Token::Operator(Operator::LessThan),
+                Token::Identifier("b".into()),
+                Token::Operator(Operator::ShiftRight),
+                Token::Number(2),
+                Token::Operator(Operator::BitwiseAnd),
+                Token::Number(3),
+                Token::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn string_literal_with_offset() {
+        let tokens = SimpleTokenizer::new("include \"lib.txt\";")
+            .with_offset(100)
+            .collect_spanned_tokens()
+            .unwrap();
+
+        assert_eq!(
+            tokens,
+            [
+                TokenNode::new(Token::Include, 100, 107),
+                TokenNode::new(Token::StringLiteral("lib.txt".into()), 108, 117),
+                TokenNode::new(Token::SemiColon, 117, 118),
+                TokenNode::new(Token::Eof, 118, 118),
+            ]
+        );
+
+        let error = SimpleTokenizer::new("include \"lib.txt;")
+            .collect_tokens()
+            .unwrap_err();
+
+        assert_eq!(error.message, "Unterminated string");
+        assert_eq!(error.span, Span { start: 8, end: 17 });
+    }
+
     #[test]
     fn peek() {
         let mut tokenizer = SimpleTokenizer::new("1 asd");
 
         assert_eq!(tokenizer.peek().unwrap(), Token::Number(1));
-        tokenizer.next().unwrap();
+        Tokenizer::next(&mut tokenizer).unwrap();
         assert_eq!(tokenizer.peek().unwrap(), Token::Identifier("asd".into()));
         assert_eq!(tokenizer.peek().unwrap(), Token::Identifier("asd".into()));
     }
+
+    #[test]
+    fn display() {
+        assert_eq!(format!("{}", Token::SemiColon), ";");
+        assert_eq!(format!("{}", Token::Number(42)), "42");
+        assert_eq!(format!("{}", Token::Identifier("x".into())), "x");
+        assert_eq!(format!("{}", Token::Operator(Operator::Plus)), "+");
+        assert_eq!(
+            format!("{}", Token::CompoundAssignment(Operator::Multiplication)),
+            "*="
+        );
+        assert_eq!(format!("{}", Operator::LessThan), "<");
+    }
+
+    #[test]
+    fn iterator() {
+        let source = "var x = (1 + 23) * 4; print(x);";
+        let tokens = SimpleTokenizer::new(source)
+            .map(|result| result.unwrap().token)
+            .collect::<Vec<Token>>();
+
+        assert_eq!(
+            tokens,
+            SimpleTokenizer::new(source).collect_tokens().unwrap()
+        );
+
+        let mut tokenizer = SimpleTokenizer::new("x");
+        assert_eq!(
+            Iterator::next(&mut tokenizer).map(|result| result.unwrap().token),
+            Some(Token::Identifier("x".into()))
+        );
+        assert_eq!(
+            Iterator::next(&mut tokenizer).map(|result| result.unwrap().token),
+            Some(Token::Eof)
+        );
+        assert_eq!(Iterator::next(&mut tokenizer), None);
+
+        let mut tokenizer = SimpleTokenizer::new("x $");
+        assert!(tokenizer.nth(1).unwrap().is_err());
+        assert_eq!(Iterator::next(&mut tokenizer), None);
+    }
+
+    #[test]
+    fn long_whitespace() {
+        let source = format!("{}x{}", " \n\t".repeat(50_000), "\n".repeat(50_000));
+
+        assert_eq!(
+            dump_tokens(&source).unwrap(),
+            "150000..150001 Identifier(\"x\")\n200001..200001 Eof\n"
+        );
+    }
+
+    #[test]
+    fn snapshot_arithmetic() {
+        assert_eq!(
+            dump_tokens("var x = (1 + 23) * 4 - y;").unwrap(),
+            "\
+0..3 Var
+4..5 Identifier(\"x\")
+6..7 Equals
+8..9 OpeningParenthesis
+9..10 Number(1)
+11..12 Operator(Plus)
+13..15 Number(23)
+15..16 ClosingParenthesis
+17..18 Operator(Multiplication)
+19..20 Number(4)
+21..22 Operator(Minus)
+23..24 Identifier(\"y\")
+24..25 SemiColon
+25..25 Eof
+"
+        );
+    }
+
+    #[test]
+    fn snapshot_loop() {
+        assert_eq!(
+            dump_tokens("while i < 10 {\n    i = i + 1;\n}").unwrap(),
+            "\
+0..5 While
+6..7 Identifier(\"i\")
+8..9 Operator(LessThan)
+10..12 Number(10)
+13..14 OpeningCurlyBracket
+19..20 Identifier(\"i\")
+21..22 Equals
+23..24 Identifier(\"i\")
+25..26 Operator(Plus)
+27..28 Number(1)
+28..29 SemiColon
+30..31 ClosingCurlyBracket
+31..31 Eof
+"
+        );
+    }
+
+    #[test]
+    fn snapshot_calls() {
+        assert_eq!(
+            dump_tokens("print(input(), variable_1);").unwrap(),
+            "\
+0..5 Identifier(\"print\")
+5..6 OpeningParenthesis
+6..11 Identifier(\"input\")
+11..12 OpeningParenthesis
+12..13 ClosingParenthesis
+13..14 Comma
+15..25 Identifier(\"variable_1\")
+25..26 ClosingParenthesis
+26..27 SemiColon
+27..27 Eof
+"
+        );
+    }
+
+    #[test]
+    fn snapshot_compound_assignment() {
+        assert_eq!(
+            dump_tokens("x += 1; x -= -2; x *= 3 + 4;").unwrap(),
+            "\
+0..1 Identifier(\"x\")
+2..4 CompoundAssignment(Plus)
+5..6 Number(1)
+6..7 SemiColon
+8..9 Identifier(\"x\")
+10..12 CompoundAssignment(Minus)
+13..14 Operator(Minus)
+14..15 Number(2)
+15..16 SemiColon
+17..18 Identifier(\"x\")
+19..21 CompoundAssignment(Multiplication)
+22..23 Number(3)
+24..25 Operator(Plus)
+26..27 Number(4)
+27..28 SemiColon
+28..28 Eof
+"
+        );
+    }
+
+    #[test]
+    fn raw_identifiers() {
+        assert_eq!(
+            dump_tokens("var `while` = `x`;").unwrap(),
+            "\
+0..3 Var
+4..11 Identifier(\"while\")
+12..13 Equals
+14..17 Identifier(\"x\")
+17..18 SemiColon
+18..18 Eof
+"
+        );
+
+        let error = SimpleTokenizer::new("x = `while;")
+            .collect_tokens()
+            .unwrap_err();
+        assert_eq!(error.message, "Unterminated raw identifier");
+        assert_eq!(error.span, Span { start: 4, end: 11 });
+
+        let error = SimpleTokenizer::new("x = `1`;")
+            .collect_tokens()
+            .unwrap_err();
+        assert_eq!(error.message, "Invalid raw identifier");
+    }
+
+    #[test]
+    fn custom_keyword() {
+        let mut rules = default_rules();
+        rules.push(TokenizerRule::String("let", Token::Var));
+
+        assert_eq!(
+            SimpleTokenizer::with_rules("let x = letter;", rules)
+                .collect_tokens()
+                .unwrap(),
+            [
+                Token::Var,
+                Token::Identifier("x".into()),
+                Token::Equals,
+                Token::Identifier("letter".into()),
+                Token::SemiColon,
+                Token::Eof,
+            ]
+        );
+        // Still an identifier with the default rules
+        assert_eq!(
+            SimpleTokenizer::new("let").collect_tokens().unwrap(),
+            [Token::Identifier("let".into()), Token::Eof]
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "i64"))]
+    fn number_boundaries() {
+        assert_eq!(
+            tokenize("2147483647").unwrap()[0],
+            TokenNode::new(Token::Number(i32::MAX), 0, 10)
+        );
+        assert_eq!(
+            tokenize("var x = 2147483648;").unwrap_err(),
+            TokenizerError::new("Number literal too large".into(), 8, 18)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "i64")]
+    fn number_boundaries() {
+        // Too large for i32, fine for i64
+        assert_eq!(
+            tokenize("2147483648").unwrap()[0],
+            TokenNode::new(Token::Number(2147483648), 0, 10)
+        );
+        assert_eq!(
+            tokenize("9223372036854775807").unwrap()[0],
+            TokenNode::new(Token::Number(i64::MAX), 0, 19)
+        );
+        assert_eq!(
+            tokenize("var x = 9223372036854775808;").unwrap_err(),
+            TokenizerError::new("Number literal too large".into(), 8, 27)
+        );
+    }
+
+    #[test]
+    fn tokenize_spans() {
+        let tokens = tokenize("var x = 1;").unwrap();
+
+        assert_eq!(
+            tokens,
+            [
+                TokenNode::new(Token::Var, 0, 3),
+                TokenNode::new(Token::Identifier("x".into()), 4, 5),
+                TokenNode::new(Token::Equals, 6, 7),
+                TokenNode::new(Token::Number(1), 8, 9),
+                TokenNode::new(Token::SemiColon, 9, 10),
+                TokenNode::new(Token::Eof, 10, 10),
+            ]
+        );
+        // Only whitespace between the tokens
+        for pair in tokens.windows(2) {
+            assert!(pair[0].span.end <= pair[1].span.start);
+            assert!("var x = 1;"[pair[0].span.end..pair[1].span.start]
+                .trim()
+                .is_empty());
+        }
+
+        let error = tokenize("var x = 1 $").unwrap_err();
+        assert_eq!(error.span, Span { start: 10, end: 11 });
+    }
+
+    #[test]
+    fn keyword_spans() {
+        assert_eq!(
+            dump_tokens("var variable = 1;\n  while\tvariable << 2 { dox = variables; }").unwrap(),
+            "\
+0..3 Var
+4..12 Identifier(\"variable\")
+13..14 Equals
+15..16 Number(1)
+16..17 SemiColon
+20..25 While
+26..34 Identifier(\"variable\")
+35..37 Operator(ShiftLeft)
+38..39 Number(2)
+40..41 OpeningCurlyBracket
+42..45 Identifier(\"dox\")
+46..47 Equals
+48..57 Identifier(\"variables\")
+57..58 SemiColon
+59..60 ClosingCurlyBracket
+60..60 Eof
+"
+        );
+
+        // A keyword is also the span of the error when it is in the wrong place
+        let error = Parser::new(SimpleTokenizer::new("var  while = 1;"))
+            .parse()
+            .unwrap_err();
+        assert_eq!(error.span, Span { start: 5, end: 10 });
+    }
+
+    #[test]
+    fn snapshot_booleans() {
+        assert_eq!(
+            dump_tokens("while true { x = false; truex = falsey; }").unwrap(),
+            "\
+0..5 While
+6..10 True
+11..12 OpeningCurlyBracket
+13..14 Identifier(\"x\")
+15..16 Equals
+17..22 False
+22..23 SemiColon
+24..29 Identifier(\"truex\")
+30..31 Equals
+32..38 Identifier(\"falsey\")
+38..39 SemiColon
+40..41 ClosingCurlyBracket
+41..41 Eof
+"
+        );
+    }
+
+    #[test]
+    fn snapshot_newlines() {
+        let source = "var x = 1\n\nprint(\n    x\n)\nwhile x {\n}";
+
+        assert_eq!(
+            dump_tokens(source).unwrap(),
+            dump_tokens(&source.replace('\n', " ")).unwrap()
+        );
+
+        let tokens = SimpleTokenizer::new(source)
+            .with_newlines()
+            .collect_spanned_tokens()
+            .unwrap()
+            .iter()
+            .map(|TokenNode { token, span }| format!("{}..{} {token:?}\n", span.start, span.end))
+            .collect::<String>();
+
+        assert_eq!(
+            tokens,
+            "\
+0..3 Var
+4..5 Identifier(\"x\")
+6..7 Equals
+8..9 Number(1)
+9..10 Newline
+11..16 Identifier(\"print\")
+16..17 OpeningParenthesis
+22..23 Identifier(\"x\")
+24..25 ClosingParenthesis
+25..26 Newline
+26..31 While
+32..33 Identifier(\"x\")
+34..35 OpeningCurlyBracket
+36..37 ClosingCurlyBracket
+37..37 Eof
+"
+        );
+    }
 }
@@ -0,0 +1,73 @@
+pub mod compiler;
+pub mod debugger;
+pub mod dump;
+pub mod evaluator;
+pub mod formatter;
+pub mod include;
+pub mod lexer;
+pub mod lint;
+pub mod metrics;
+pub mod optimizer;
+pub mod parser;
+pub mod pipeline;
+pub mod repl;
+pub mod runtime;
+pub mod session;
+pub mod sexpr;
+pub mod source_map;
+pub mod symbol;
+pub mod utils;
+pub mod vm;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use evaluator::{CustomFunction, Evaluator, RuntimeError, State, Stop};
+pub use lexer::{tokenize, SimpleTokenizer, TokenNode, TokenizerError};
+pub use parser::{Parser, ParserError, Program};
+pub use pipeline::{analyze_and_run, RunOptions, RunReport};
+pub use runtime::SharedBuffer;
+pub use symbol::Symbol;
+pub use utils::{Num, SpanError};
+
+use std::panic;
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum RunError {
+    // Parser or runtime error of the program
+    Program(SpanError),
+    // Panic inside the interpreter, with the panic message
+    Internal(String),
+}
+
+pub fn run(source: &str) -> Result<(), SpanError> {
+    run_with_state(source, &mut State::new())
+}
+
+// Like `run`, but a panic is returned as `RunError::Internal` instead of unwinding into the host.
+// This is a safety net against interpreter bugs, errors of the program are still reported as
+// `RunError::Program`.
+pub fn run_catch_unwind(source: &str) -> Result<(), RunError> {
+    run_catch_unwind_with_state(source, &mut State::new())
+}
+
+// Like `run_catch_unwind` with a state of the host, so panics of host functions are caught too.
+// After a panic the state may be left halfway through a line.
+pub fn run_catch_unwind_with_state(source: &str, state: &mut State) -> Result<(), RunError> {
+    match panic::catch_unwind(panic::AssertUnwindSafe(|| run_with_state(source, state))) {
+        Ok(result) => result.map_err(RunError::Program),
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "Unknown panic".into());
+            Err(RunError::Internal(message))
+        }
+    }
+}
+
+pub fn run_with_state(source: &str, state: &mut State) -> Result<(), SpanError> {
+    let program = Parser::new(SimpleTokenizer::new(source)).parse()?;
+
+    Evaluator::new().evaluate_with_state(state, program)
+}
@@ -0,0 +1,392 @@
+use std::collections::HashMap;
+
+use crate::{
+    evaluator::{builtin_functions, CustomFunction},
+    parser::{AstNode, Expression, FunctionCall, Line, Program},
+    symbol::Symbol,
+    utils::{Span, SpanError},
+};
+
+pub type LintWarning = SpanError;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Lint {
+    // A variable declared with `var` that is never read, also when it is reassigned
+    UnusedVariable,
+    // `while x = 0 { }`, where a comparison was probably meant
+    AssignmentInCondition,
+    // `var print = 5;`, a variable with the name of a built-in function
+    ShadowedFunction,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 3] = [
+        Lint::UnusedVariable,
+        Lint::AssignmentInCondition,
+        Lint::ShadowedFunction,
+    ];
+}
+
+// Warnings of the selected lints, in source order. Variables are checked against the built-in
+// functions, `lint_with_functions` takes the functions of a state with host functions.
+pub fn lint(program: &AstNode<Program>, lints: &[Lint]) -> Vec<LintWarning> {
+    lint_with_functions(program, lints, &builtin_functions())
+}
+
+pub fn lint_with_functions(
+    program: &AstNode<Program>,
+    lints: &[Lint],
+    functions: &HashMap<Symbol, CustomFunction>,
+) -> Vec<LintWarning> {
+    let mut warnings = Vec::new();
+
+    if lints.contains(&Lint::UnusedVariable) {
+        warnings.extend(unused_variables(program));
+    }
+    if lints.contains(&Lint::AssignmentInCondition) {
+        assignments_in_conditions(&program.node.lines, &mut warnings);
+    }
+    if lints.contains(&Lint::ShadowedFunction) {
+        warnings.extend(shadowed_functions(program, functions));
+    }
+
+    warnings.sort_by_key(|warning| warning.span.start);
+    warnings
+}
+
+fn unused_variables(AstNode { node: program, .. }: &AstNode<Program>) -> Vec<LintWarning> {
+    let mut usage = Usage::default();
+    collect_lines(&program.lines, &mut usage);
+
+    usage
+        .declarations
+        .into_iter()
+        .filter(|declaration| !declaration.read)
+        .map(
+            |Declaration {
+                 name,
+                 span,
+                 written,
+                 ..
+             }| LintWarning {
+                message: if written {
+                    format!("Variable {name} is assigned but never read")
+                } else {
+                    format!("Variable {name} is never used")
+                },
+                span,
+            },
+        )
+        .collect()
+}
+
+// Variables named like one of the functions
+pub fn shadowed_functions(
+    AstNode { node: program, .. }: &AstNode<Program>,
+    functions: &HashMap<Symbol, CustomFunction>,
+) -> Vec<LintWarning> {
+    let mut usage = Usage::default();
+    collect_lines(&program.lines, &mut usage);
+
+    usage
+        .declarations
+        .into_iter()
+        .filter(|declaration| functions.contains_key(&declaration.name))
+        .map(|Declaration { name, span, .. }| LintWarning {
+            message: format!("Variable {name} has the same name as a function"),
+            span,
+        })
+        .collect()
+}
+
+fn assignments_in_conditions(lines: &[AstNode<Line>], warnings: &mut Vec<LintWarning>) {
+    for AstNode { node: line, .. } in lines {
+        match line {
+            Line::Loop(condition, lines) | Line::DoWhile(lines, condition) => {
+                if let Expression::Assign(name, _) = condition.node {
+                    warnings.push(LintWarning {
+                        message: format!("Assignment to {name} in a loop condition"),
+                        span: condition.span,
+                    });
+                }
+                assignments_in_conditions(lines, warnings);
+            }
+            Line::Match(_, arms) => {
+                for (_, lines) in arms {
+                    assignments_in_conditions(lines, warnings);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+struct Declaration {
+    name: Symbol,
+    span: Span,
+    read: bool,
+    // Reassigned after the declaration
+    written: bool,
+}
+
+// Follows the scopes of the evaluator, so a use refers to the declaration it would find at run
+// time and a variable in a loop body does not count as a use of an outer one with the same name
+struct Usage {
+    declarations: Vec<Declaration>,
+    // Indices into `declarations` of the variables in each scope, the innermost scope is last
+    scopes: Vec<HashMap<Symbol, usize>>,
+}
+
+impl Default for Usage {
+    fn default() -> Self {
+        Usage {
+            declarations: Vec::new(),
+            scopes: vec![HashMap::new()],
+        }
+    }
+}
+
+impl Usage {
+    fn declare(&mut self, name: Symbol, span: Span) {
+        self.scopes
+            .last_mut()
+            .expect("the program scope is never popped")
+            .insert(name, self.declarations.len());
+        self.declarations.push(Declaration {
+            name,
+            span,
+            read: false,
+            written: false,
+        });
+    }
+
+    // Undefined variables are a runtime error, not something to warn about here
+    fn resolve(&mut self, name: &Symbol) -> Option<&mut Declaration> {
+        let index = *self.scopes.iter().rev().find_map(|scope| scope.get(name))?;
+        Some(&mut self.declarations[index])
+    }
+
+    fn read(&mut self, name: &Symbol) {
+        if let Some(declaration) = self.resolve(name) {
+            declaration.read = true;
+        }
+    }
+
+    fn write(&mut self, name: &Symbol) {
+        if let Some(declaration) = self.resolve(name) {
+            declaration.written = true;
+        }
+    }
+
+    fn collect_body(&mut self, lines: &[AstNode<Line>]) {
+        self.scopes.push(HashMap::new());
+        collect_lines(lines, self);
+        self.scopes.pop();
+    }
+}
+
+fn collect_lines(lines: &[AstNode<Line>], usage: &mut Usage) {
+    for AstNode { node: line, span } in lines {
+        match line {
+            // The value is evaluated before the variable exists
+            Line::Assignment(name, expression) | Line::ConstAssignment(name, expression) => {
+                collect_expression(expression, usage);
+                usage.declare(*name, *span);
+            }
+            Line::MultiAssignment(names, values) => {
+                for value in values {
+                    collect_expression(value, usage);
+                }
+                for name in names {
+                    usage.declare(*name, *span);
+                }
+            }
+            Line::Reassignment(name, expression) => {
+                collect_expression(expression, usage);
+                usage.write(name);
+            }
+            Line::Call(function_call) => collect_function_call(function_call, usage),
+            Line::Expression(expression) => collect_expression(expression, usage),
+            // The condition is outside the scope of the body
+            Line::Loop(condition, lines) | Line::DoWhile(lines, condition) => {
+                collect_expression(condition, usage);
+                usage.collect_body(lines);
+            }
+            Line::Match(scrutinee, arms) => {
+                collect_expression(scrutinee, usage);
+                for (_, lines) in arms {
+                    usage.collect_body(lines);
+                }
+            }
+            Line::Include(_) | Line::Error => {}
+        }
+    }
+}
+
+fn collect_expression(
+    AstNode {
+        node: expression, ..
+    }: &AstNode<Expression>,
+    usage: &mut Usage,
+) {
+    match expression {
+        Expression::Identifier(name) => usage.read(name),
+        Expression::BinaryOperator(left, _, right) => {
+            collect_expression(left, usage);
+            collect_expression(right, usage);
+        }
+        Expression::Comparison(first, comparisons) => {
+            collect_expression(first, usage);
+            for (_, operand) in comparisons {
+                collect_expression(operand, usage);
+            }
+        }
+        Expression::Call(function_call) => collect_function_call(function_call, usage),
+        Expression::Assign(name, value) => {
+            collect_expression(value, usage);
+            usage.write(name);
+        }
+        Expression::Conditional(condition, then, otherwise) => {
+            collect_expression(condition, usage);
+            collect_expression(then, usage);
+            collect_expression(otherwise, usage);
+        }
+        Expression::Number(_) | Expression::Bool(_) | Expression::Error => {}
+    }
+}
+
+fn collect_function_call(
+    AstNode {
+        node: function_call,
+        ..
+    }: &AstNode<FunctionCall>,
+    usage: &mut Usage,
+) {
+    for argument in &function_call.arguments {
+        collect_expression(argument, usage);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{evaluator::State, lexer::SimpleTokenizer, parser::Parser, utils::Span};
+
+    use super::{lint, lint_with_functions, Lint};
+
+    #[test]
+    fn unused_variables() {
+        let program = Parser::new(SimpleTokenizer::new(
+            "var a = 1; var b = 2; var c = 3; b = a; while c { var d = 0; c = 0; }",
+        ))
+        .parse()
+        .unwrap();
+        let warnings = lint(&program, &Lint::ALL);
+
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].message, "Variable b is assigned but never read");
+        assert_eq!(warnings[0].span, Span { start: 11, end: 21 });
+        assert_eq!(warnings[1].message, "Variable d is never used");
+
+        assert!(lint(&program, &[]).is_empty());
+    }
+
+    #[test]
+    fn shadowed_function() {
+        let program = Parser::new(SimpleTokenizer::new(
+            "var print = 5; var printed = print; while 0 { var a, max = 1, 2; }",
+        ))
+        .parse()
+        .unwrap();
+        let warnings = lint(&program, &[Lint::ShadowedFunction]);
+
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(
+            warnings[0].message,
+            "Variable print has the same name as a function"
+        );
+        assert_eq!(warnings[0].span, Span { start: 0, end: 14 });
+        assert_eq!(
+            warnings[1].message,
+            "Variable max has the same name as a function"
+        );
+
+        // Host functions of the state are checked as well
+        let program = Parser::new(SimpleTokenizer::new("var add = 1;"))
+            .parse()
+            .unwrap();
+        assert!(lint(&program, &[Lint::ShadowedFunction]).is_empty());
+
+        let mut state = State::new();
+        state.register_function("add", |_, _, _| Ok(0));
+        let warnings = lint_with_functions(&program, &[Lint::ShadowedFunction], &state.functions);
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn shadowed_variable() {
+        // The reads in the bodies are of the inner `x`, the outer one is never read
+        let program = Parser::new(SimpleTokenizer::new(
+            "var x = 1; var i = 0; while i < 2 { var x = i; print(x); i = i + 1; } \
+             match i { 2 => { var x = 3; print(x); } }",
+        ))
+        .parse()
+        .unwrap();
+        let warnings = lint(&program, &[Lint::UnusedVariable]);
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].message, "Variable x is never used");
+        assert_eq!(warnings[0].span, Span { start: 0, end: 10 });
+
+        // An outer variable read in the body is used
+        let program = Parser::new(SimpleTokenizer::new(
+            "var x = 1; var i = 0; while i < 2 { print(x); i = i + 1; }",
+        ))
+        .parse()
+        .unwrap();
+        assert!(lint(&program, &[Lint::UnusedVariable]).is_empty());
+
+        // `var x = x + 1;` in a body reads the outer `x` before the inner one exists
+        let program = Parser::new(SimpleTokenizer::new(
+            "var x = 1; while 0 { var x = x + 1; }",
+        ))
+        .parse()
+        .unwrap();
+        let warnings = lint(&program, &[Lint::UnusedVariable]);
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].span, Span { start: 21, end: 35 });
+    }
+
+    #[test]
+    fn write_only_variable() {
+        let program = Parser::new(SimpleTokenizer::new(
+            "var a = 1; var b = 0; a = 2; print(b = a);",
+        ))
+        .parse()
+        .unwrap();
+        let warnings = lint(&program, &[Lint::UnusedVariable]);
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].message, "Variable b is assigned but never read");
+        assert_eq!(warnings[0].span, Span { start: 11, end: 21 });
+
+        let used = Parser::new(SimpleTokenizer::new("var a = 1; a = a + 1; print(a);"))
+            .parse()
+            .unwrap();
+        assert!(lint(&used, &[Lint::UnusedVariable]).is_empty());
+    }
+
+    #[test]
+    fn assignment_in_condition() {
+        let program = Parser::new(SimpleTokenizer::new(
+            "var x = 1; while x = 0 { while (x = 1) < 0 { } }",
+        ))
+        .parse()
+        .unwrap();
+        let warnings = lint(&program, &[Lint::AssignmentInCondition]);
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].message, "Assignment to x in a loop condition");
+        assert_eq!(warnings[0].span, Span { start: 17, end: 22 });
+    }
+}
@@ -1,41 +1,373 @@
-use evaluator::Evaluator;
-use lexer::SimpleTokenizer;
-use parser::Parser;
+use simple_interpreter::debugger::run_debugger;
+use simple_interpreter::dump::{dot, dump};
+use simple_interpreter::include::Loader;
+use simple_interpreter::lexer::dump_tokens;
+use simple_interpreter::lint::{lint_with_functions, Lint, LintWarning};
+use simple_interpreter::parser::AstNode;
+use simple_interpreter::repl::run_repl;
+use simple_interpreter::source_map::{Diagnostic, Severity, SourceMap};
+use simple_interpreter::utils::{format_error, MainError, SpanError};
+use simple_interpreter::{Evaluator, Parser, Program, SimpleTokenizer, State};
 use std::env;
 use std::fs;
-use utils::format_error;
-use utils::MainError;
+use std::io::{self, BufReader, Cursor, IsTerminal, Read};
+use std::path::Path;
+use std::process;
 
-mod evaluator;
-mod lexer;
-mod parser;
-mod runtime;
-mod utils;
+const USAGE: &str =
+    "Usage: simple-interpreter [--repl | --tokens | --ast | --ast-json | --dot | --parse-only --emit=debug | \
+    --debug] [--max-steps <n>] [--seed <n>] [--strict-semicolons | --optional-semicolons] [--warn-unused] \
+    [--diagnostics=json] [path | -] [input path with --debug]";
+
+// How `run_file` reports errors
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum Diagnostics {
+    // The message with the file, line and column, returned from `main`
+    Text,
+    // Every parser error, or the runtime error, as a JSON array on stderr for editors
+    Json,
+}
+
+// What to do with the program, `Run` unless a flag selects something else
+#[derive(PartialEq, Eq)]
+enum Mode {
+    Run,
+    Repl,
+    Tokens,
+    Ast,
+    AstJson,
+    Dot,
+    EmitDebug,
+    Debug,
+}
+
+// The command line. Flags can be combined and given in any order, like
+// `--seed 42 --max-steps 1000 --warn-unused example.txt`.
+struct Options {
+    mode: Mode,
+    // The program, with `-` for stdin
+    path: Option<String>,
+    // Read by `input` in `--debug` mode
+    input_path: Option<String>,
+    max_steps: Option<u64>,
+    seed: Option<u64>,
+    optional_semicolons: bool,
+    lints: Vec<Lint>,
+    diagnostics: Diagnostics,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Options, MainError> {
+        let mut options = Options {
+            mode: Mode::Run,
+            path: None,
+            input_path: None,
+            max_steps: None,
+            seed: None,
+            optional_semicolons: false,
+            lints: Vec::new(),
+            diagnostics: Diagnostics::Text,
+        };
+        let mut parse_only = false;
+        let mut paths = Vec::new();
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mode = match arg.as_str() {
+                "--repl" => Mode::Repl,
+                "--tokens" => Mode::Tokens,
+                "--ast" => Mode::Ast,
+                "--ast-json" => Mode::AstJson,
+                "--dot" => Mode::Dot,
+                "--emit=debug" => Mode::EmitDebug,
+                "--debug" => Mode::Debug,
+                "--parse-only" => {
+                    parse_only = true;
+                    continue;
+                }
+                "--max-steps" => {
+                    let value = args.next().ok_or_else(|| USAGE.to_string())?;
+                    let max_steps = value
+                        .parse()
+                        .map_err(|err| format!("Invalid --max-steps value {value}: {err}"))?;
+                    options.max_steps = Some(max_steps);
+                    continue;
+                }
+                "--seed" => {
+                    let value = args.next().ok_or_else(|| USAGE.to_string())?;
+                    let seed = value
+                        .parse()
+                        .map_err(|err| format!("Invalid --seed value {value}: {err}"))?;
+                    options.seed = Some(seed);
+                    continue;
+                }
+                "--strict-semicolons" => {
+                    options.optional_semicolons = false;
+                    continue;
+                }
+                "--optional-semicolons" => {
+                    options.optional_semicolons = true;
+                    continue;
+                }
+                "--warn-unused" => {
+                    options.lints.push(Lint::UnusedVariable);
+                    continue;
+                }
+                "--diagnostics=json" => {
+                    options.diagnostics = Diagnostics::Json;
+                    continue;
+                }
+                flag if flag.starts_with('-') && flag != "-" => {
+                    return Err(USAGE.to_string().into())
+                }
+                _ => {
+                    paths.push(arg.clone());
+                    continue;
+                }
+            };
+
+            // Only one mode at a time
+            if options.mode != Mode::Run {
+                return Err(USAGE.to_string().into());
+            }
+            options.mode = mode;
+        }
+
+        // `--parse-only` only goes with `--emit=debug`, which never runs the program anyway
+        if parse_only && options.mode != Mode::EmitDebug {
+            return Err(USAGE.to_string().into());
+        }
+
+        // Only the debugger takes a second file
+        let mut paths = paths.into_iter();
+        options.path = paths.next();
+        if options.mode == Mode::Debug {
+            options.input_path = paths.next();
+        }
+        if paths.next().is_some() {
+            return Err(USAGE.to_string().into());
+        }
+
+        Ok(options)
+    }
+
+    fn evaluator(&self) -> Evaluator {
+        match self.max_steps {
+            Some(max_steps) => Evaluator::new().with_max_steps(max_steps),
+            None => Evaluator::new(),
+        }
+    }
+
+    fn state<'a>(&self, mut state: State<'a>) -> State<'a> {
+        if let Some(seed) = self.seed {
+            state.set_seed(seed);
+        }
+        state
+    }
+}
 
 fn main() -> Result<(), MainError> {
-    const DEFAULT_PATH: &str = "example.txt";
-    let args: Vec<String> = env::args().collect();
-    let path = args.get(1).map(String::as_str).unwrap_or(DEFAULT_PATH);
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = Options::parse(&args)?;
+
+    match (&options.mode, options.path.as_deref()) {
+        // A program piped in, like `echo 'print(1);' | simple-interpreter`
+        (Mode::Run, None) if !io::stdin().is_terminal() => run_file("-", &options),
+        (Mode::Run | Mode::Repl, None) => Ok(run_repl(&mut options.state(State::new()))?),
+        (Mode::Run, Some(path)) => run_file(path, &options),
+        (Mode::Tokens, Some(path)) => print_tokens(path),
+        (Mode::Ast, Some(path)) => print_ast(path),
+        (Mode::AstJson, Some(path)) => print_ast_json(path),
+        (Mode::Dot, Some(path)) => print_dot(path),
+        (Mode::EmitDebug, Some(path)) => print_ast_debug(path),
+        (Mode::Debug, Some(path)) => debug_file(path, &options),
+        _ => Err(USAGE.to_string().into()),
+    }
+}
 
-    let content = fs::read_to_string(path).map_err(|err| format!("Can not read file: {err}"))?;
+// `-` reads the program from stdin until EOF
+fn read_source(path: &str) -> Result<String, MainError> {
+    if path == "-" {
+        let mut content = String::new();
+        io::stdin()
+            .read_to_string(&mut content)
+            .map_err(|err| format!("Can not read stdin: {err}"))?;
+        return Ok(content);
+    }
 
-    let tokenizer = SimpleTokenizer::new(&content);
-    let mut parser = Parser::new(tokenizer);
+    Ok(fs::read_to_string(path).map_err(|err| format!("Can not read file: {err}"))?)
+}
 
-    println!("Parsing...");
+fn parse_file(path: &str) -> Result<AstNode<Program>, MainError> {
+    let content = read_source(path)?;
 
-    let program = parser
+    Ok(Parser::new(SimpleTokenizer::new(&content))
+        .parse()
+        .map_err(|ref err| format_error(err, &content))?)
+}
+
+fn print_tokens(path: &str) -> Result<(), MainError> {
+    let content = read_source(path)?;
+
+    let tokens = dump_tokens(&content).map_err(|ref err| format_error(err, &content))?;
+    print!("{tokens}");
+
+    Ok(())
+}
+
+fn print_ast(path: &str) -> Result<(), MainError> {
+    let program = parse_file(path)?;
+
+    print!("{}", dump(&program));
+
+    Ok(())
+}
+
+fn print_dot(path: &str) -> Result<(), MainError> {
+    let program = parse_file(path)?;
+
+    print!("{}", dot(&program));
+
+    Ok(())
+}
+
+fn print_ast_json(path: &str) -> Result<(), MainError> {
+    let program = parse_file(path)?;
+
+    let json = serde_json::to_string_pretty(&program)
+        .map_err(|err| format!("Can not serialize the program: {err}"))?;
+    println!("{json}");
+
+    Ok(())
+}
+
+fn print_ast_debug(path: &str) -> Result<(), MainError> {
+    let program = parse_file(path)?;
+
+    println!("{program:#?}");
+
+    Ok(())
+}
+
+// Debugger commands are read from stdin, so `input` reads from `input_path` instead
+fn debug_file(path: &str, options: &Options) -> Result<(), MainError> {
+    let content = read_source(path)?;
+    let input = match &options.input_path {
+        Some(input_path) => fs::read_to_string(input_path)
+            .map_err(|err| format!("Can not read input file: {err}"))?,
+        None => String::new(),
+    };
+
+    let program = Parser::new(SimpleTokenizer::new(&content))
         .parse()
         .map_err(|ref err| format_error(err, &content))?;
 
+    let mut state = options.state(State::with_io(Cursor::new(input), io::stdout()));
+
+    Ok(run_debugger(
+        &content,
+        program,
+        &mut state,
+        &options.evaluator(),
+        &mut BufReader::new(io::stdin()),
+        &mut io::stdout(),
+    )?)
+}
+
+fn run_file(path: &str, options: &Options) -> Result<(), MainError> {
+    let diagnostics = options.diagnostics;
+    let mut state = options.state(State::new());
+    let mut loader = if options.optional_semicolons {
+        Loader::new().with_optional_semicolons()
+    } else {
+        Loader::new()
+    };
+    if diagnostics == Diagnostics::Json {
+        loader = loader.with_error_recovery();
+    }
+
+    println!("Parsing...");
+
+    let program = if path == "-" {
+        let content = read_source(path)?;
+        loader.load_source("stdin", &content, Path::new("."))
+    } else {
+        loader.load(Path::new(path))
+    };
+    // Include errors still stop the loader, they come after the parser errors found until then
+    let mut errors = loader.take_errors();
+    let program = match program {
+        Ok(program) if errors.is_empty() => program,
+        Ok(_) => return Err(report(loader.source_map(), &[], &errors, diagnostics)),
+        Err(error) => {
+            errors.push(error);
+            return Err(report(loader.source_map(), &[], &errors, diagnostics));
+        }
+    };
+
+    // Warnings do not stop the program
+    // Shadowing a function is always reported, the other lints are opt-in
+    let lints = [&options.lints[..], &[Lint::ShadowedFunction]].concat();
+    let warnings = lint_with_functions(&program, &lints, &state.functions);
+    if diagnostics == Diagnostics::Text {
+        for warning in &warnings {
+            eprintln!("Warning: {}", loader.source_map().format_error(warning));
+        }
+    }
+
     println!("Starting...");
 
-    let evaluator = Evaluator {};
-    evaluator
-        .evaluate(program)
-        .map_err(|ref err| format_error(err, &content))?;
+    options
+        .evaluator()
+        .evaluate_with_state(&mut state, program)
+        .map_err(|err| report(loader.source_map(), &warnings, &[err], diagnostics))?;
+
+    // JSON warnings go in the array with the errors, so they are only printed here without one
+    if diagnostics == Diagnostics::Json && !warnings.is_empty() {
+        print_diagnostics(loader.source_map(), &warnings, &[]);
+    }
+
+    if let Some(code) = state.exit_code {
+        // Not the same type with the `i64` feature
+        #[allow(clippy::unnecessary_cast)]
+        process::exit(code as i32);
+    }
 
     println!("Success!");
 
     Ok(())
 }
+
+// Text returns the first error, there is only one without error recovery, the warnings were
+// printed before running. JSON prints the warnings and all errors and exits, so `main` does not
+// print the error again.
+fn report(
+    source_map: &SourceMap,
+    warnings: &[LintWarning],
+    errors: &[SpanError],
+    diagnostics: Diagnostics,
+) -> MainError {
+    match diagnostics {
+        Diagnostics::Text => source_map.format_error(&errors[0]).into(),
+        Diagnostics::Json => {
+            print_diagnostics(source_map, warnings, errors);
+            process::exit(1);
+        }
+    }
+}
+
+fn print_diagnostics(source_map: &SourceMap, warnings: &[LintWarning], errors: &[SpanError]) {
+    let diagnostics: Vec<Diagnostic> = warnings
+        .iter()
+        .map(|warning| source_map.diagnostic(warning, Severity::Warning))
+        .chain(
+            errors
+                .iter()
+                .map(|error| source_map.diagnostic(error, Severity::Error)),
+        )
+        .collect();
+    eprintln!(
+        "{}",
+        serde_json::to_string(&diagnostics).expect("diagnostics are serializable")
+    );
+}
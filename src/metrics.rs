@@ -0,0 +1,141 @@
+use crate::parser::{AstNode, Expression, FunctionCall, Line, Pattern, Program};
+
+// Decision points of the program plus one, every loop and every `?:` is a decision point
+pub fn cyclomatic_complexity(AstNode { node: program, .. }: &AstNode<Program>) -> u32 {
+    decision_points(&program.lines) + 1
+}
+
+fn decision_points(lines: &[AstNode<Line>]) -> u32 {
+    lines
+        .iter()
+        .map(|AstNode { node: line, .. }| match line {
+            Line::Loop(condition, lines) | Line::DoWhile(lines, condition) => {
+                1 + expression_decision_points(condition) + decision_points(lines)
+            }
+            // Every arm is a path, and so is matching nothing unless the last arm is `_`. One of
+            // the paths is there without any decision.
+            Line::Match(scrutinee, arms) => {
+                let paths = arms
+                    .iter()
+                    .map(|(_, lines)| 1 + decision_points(lines))
+                    .sum::<u32>();
+                let paths = match arms.last() {
+                    Some((Pattern::Wildcard, _)) => paths - 1,
+                    _ => paths,
+                };
+                expression_decision_points(scrutinee) + paths
+            }
+            Line::Assignment(_, expression)
+            | Line::ConstAssignment(_, expression)
+            | Line::Reassignment(_, expression)
+            | Line::Expression(expression) => expression_decision_points(expression),
+            Line::MultiAssignment(_, values) => values.iter().map(expression_decision_points).sum(),
+            Line::Call(function_call) => call_decision_points(function_call),
+            Line::Include(_) | Line::Error => 0,
+        })
+        .sum()
+}
+
+// `?:` is the `if` of the language
+fn expression_decision_points(
+    AstNode {
+        node: expression, ..
+    }: &AstNode<Expression>,
+) -> u32 {
+    match expression {
+        Expression::Conditional(condition, then, otherwise) => {
+            1 + expression_decision_points(condition)
+                + expression_decision_points(then)
+                + expression_decision_points(otherwise)
+        }
+        Expression::BinaryOperator(left, _, right) => {
+            expression_decision_points(left) + expression_decision_points(right)
+        }
+        Expression::Comparison(first, comparisons) => {
+            expression_decision_points(first)
+                + comparisons
+                    .iter()
+                    .map(|(_, operand)| expression_decision_points(operand))
+                    .sum::<u32>()
+        }
+        Expression::Assign(_, value) => expression_decision_points(value),
+        Expression::Call(function_call) => call_decision_points(function_call),
+        Expression::Number(_)
+        | Expression::Bool(_)
+        | Expression::Identifier(_)
+        | Expression::Error => 0,
+    }
+}
+
+fn call_decision_points(
+    AstNode {
+        node: function_call,
+        ..
+    }: &AstNode<FunctionCall>,
+) -> u32 {
+    function_call
+        .arguments
+        .iter()
+        .map(expression_decision_points)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{lexer::SimpleTokenizer, parser::Parser};
+
+    use super::cyclomatic_complexity;
+
+    fn complexity(source: &str) -> u32 {
+        cyclomatic_complexity(&Parser::new(SimpleTokenizer::new(source)).parse().unwrap())
+    }
+
+    #[test]
+    fn straight_line() {
+        assert_eq!(complexity(""), 1);
+        assert_eq!(complexity("var x = 1; x = x + 1; print(x);"), 1);
+    }
+
+    #[test]
+    fn loops() {
+        assert_eq!(complexity("while 0 { } while 0 { }"), 3);
+        assert_eq!(
+            complexity("while 0 { while 0 { while 0 { } } print(); } while 0 { }"),
+            5
+        );
+        assert_eq!(complexity("do { } while (0);"), 2);
+        assert_eq!(
+            complexity("while 0 { do { do { } while (0); } while (0); } do { } while (0);"),
+            5
+        );
+    }
+
+    #[test]
+    fn conditionals() {
+        assert_eq!(complexity("var a = 1; var x = a ? 1 : 2;"), 2);
+        // Nested in either branch or in the condition
+        assert_eq!(complexity("var x = 1 ? (0 ? 1 : 2) : (1 ? 3 : 4);"), 4);
+        assert_eq!(complexity("var x = (1 ? 0 : 1) ? 1 : 2;"), 3);
+        // Anywhere an expression goes
+        assert_eq!(
+            complexity(
+                "var x = 0; x = x ? 1 : 2; print(x ? 1 : 2, x); while x ? 0 : 0 { } \
+                 match x ? 1 : 2 { _ => { } } do { } while (x = x ? 0 : 0);"
+            ),
+            8
+        );
+    }
+
+    #[test]
+    fn matches() {
+        // Without `_` nothing matching is a path as well
+        assert_eq!(complexity("match 1 { 1 => { } }"), 2);
+        assert_eq!(complexity("match 1 { 1 => { } 2 => { } }"), 3);
+        assert_eq!(complexity("match 1 { 1 => { } _ => { } }"), 2);
+        assert_eq!(complexity("match 1 { _ => { } }"), 1);
+        assert_eq!(
+            complexity("match 1 { 1 => { while 0 { } } _ => { match 2 { 2 => { } } } }"),
+            4
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use crate::{
+    evaluator::evaluate_operator,
+    lexer::Operator,
+    parser::{AstNode, Expression, FunctionCall, Line, Program},
+    utils::{Num, Span},
+};
+
+// Replaces operators on constant operands with their result. Operations that would
+// fail, like an overflow, are kept so the error is still reported at runtime.
+pub fn fold_constants(
+    AstNode {
+        node: program,
+        span,
+    }: AstNode<Program>,
+) -> AstNode<Program> {
+    AstNode {
+        node: Program {
+            lines: fold_lines(program.lines),
+        },
+        span,
+    }
+}
+
+fn fold_lines(lines: Vec<AstNode<Line>>) -> Vec<AstNode<Line>> {
+    lines.into_iter().map(fold_line).collect()
+}
+
+fn fold_line(AstNode { node: line, span }: AstNode<Line>) -> AstNode<Line> {
+    let node = match line {
+        Line::Assignment(name, expression) => Line::Assignment(name, fold_expression(expression)),
+        Line::MultiAssignment(names, values) => {
+            Line::MultiAssignment(names, values.into_iter().map(fold_expression).collect())
+        }
+        Line::ConstAssignment(name, expression) => {
+            Line::ConstAssignment(name, fold_expression(expression))
+        }
+        Line::Reassignment(name, expression) => {
+            Line::Reassignment(name, fold_expression(expression))
+        }
+        Line::Call(function_call) => Line::Call(fold_function_call(function_call)),
+        Line::Expression(expression) => Line::Expression(fold_expression(expression)),
+        Line::Loop(condition, lines) => Line::Loop(fold_expression(condition), fold_lines(lines)),
+        Line::Match(scrutinee, arms) => Line::Match(
+            fold_expression(scrutinee),
+            arms.into_iter()
+                .map(|(pattern, lines)| (pattern, fold_lines(lines)))
+                .collect(),
+        ),
+        Line::DoWhile(lines, condition) => {
+            Line::DoWhile(fold_lines(lines), fold_expression(condition))
+        }
+        Line::Include(path) => Line::Include(path),
+        Line::Error => Line::Error,
+    };
+
+    AstNode { node, span }
+}
+
+pub fn fold_expression(
+    AstNode {
+        node: expression,
+        span,
+    }: AstNode<Expression>,
+) -> AstNode<Expression> {
+    let node = match expression {
+        Expression::BinaryOperator(left, operator, right) => {
+            let left = fold_expression(*left);
+            let right = fold_expression(*right);
+
+            let folded = match (&left.node, &right.node) {
+                (Expression::Number(left), Expression::Number(right)) => {
+                    evaluate_operator(operator, *left, *right, span).ok()
+                }
+                _ => None,
+            };
+
+            match folded {
+                Some(value) => Expression::Number(value),
+                None => Expression::BinaryOperator(Box::new(left), operator, Box::new(right)),
+            }
+        }
+        Expression::Comparison(first, comparisons) => fold_comparison(*first, comparisons, span),
+        Expression::Call(function_call) => Expression::Call(fold_function_call(function_call)),
+        Expression::Assign(name, value) => {
+            Expression::Assign(name, Box::new(fold_expression(*value)))
+        }
+        Expression::Conditional(condition, then, otherwise) => {
+            let condition = fold_expression(*condition);
+            let then = fold_expression(*then);
+            let otherwise = fold_expression(*otherwise);
+
+            let constant = match condition.node {
+                Expression::Number(value) => Some(value != 0),
+                Expression::Bool(value) => Some(value),
+                _ => None,
+            };
+
+            match constant {
+                // The branch keeps its own span
+                Some(true) => return then,
+                Some(false) => return otherwise,
+                None => Expression::Conditional(
+                    Box::new(condition),
+                    Box::new(then),
+                    Box::new(otherwise),
+                ),
+            }
+        }
+        expression => expression,
+    };
+
+    AstNode { node, span }
+}
+
+// A chain of constants becomes a number like a single comparison does
+fn fold_comparison(
+    first: AstNode<Expression>,
+    comparisons: Vec<(Operator, AstNode<Expression>)>,
+    span: Span,
+) -> Expression {
+    let first = fold_expression(first);
+    let comparisons: Vec<_> = comparisons
+        .into_iter()
+        .map(|(operator, operand)| (operator, fold_expression(operand)))
+        .collect();
+
+    let values: Option<Vec<Num>> = std::iter::once(&first)
+        .chain(comparisons.iter().map(|(_, operand)| operand))
+        .map(|operand| match operand.node {
+            Expression::Number(value) => Some(value),
+            _ => None,
+        })
+        .collect();
+
+    match values {
+        Some(values) => {
+            let holds = comparisons
+                .iter()
+                .zip(values.windows(2))
+                .all(|((operator, _), pair)| {
+                    evaluate_operator(*operator, pair[0], pair[1], span) == Ok(1)
+                });
+            Expression::Number(holds as Num)
+        }
+        None => Expression::Comparison(Box::new(first), comparisons),
+    }
+}
+
+fn fold_function_call(
+    AstNode {
+        node: function_call,
+        span,
+    }: AstNode<FunctionCall>,
+) -> AstNode<FunctionCall> {
+    AstNode {
+        node: FunctionCall {
+            name: function_call.name,
+            arguments: function_call
+                .arguments
+                .into_iter()
+                .map(fold_expression)
+                .collect(),
+        },
+        span,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        evaluator::Evaluator,
+        formatter::format_expression,
+        lexer::SimpleTokenizer,
+        parser::{AstNode, Expression, Parser},
+        utils::{Num, Span},
+    };
+
+    use super::{fold_constants, fold_expression};
+
+    fn parse(source: &str) -> AstNode<Expression> {
+        Parser::new(SimpleTokenizer::new(source))
+            .parse_single_expression()
+            .unwrap()
+    }
+
+    #[test]
+    fn fold_nested_operators() {
+        let folded = fold_expression(parse("1 + 2 * 3"));
+
+        assert_eq!(
+            folded,
+            AstNode {
+                node: Expression::Number(7),
+                span: Span { start: 0, end: 9 },
+            }
+        );
+    }
+
+    #[test]
+    fn fold_constant_operands_only() {
+        let folded = fold_expression(parse("x + 2 * 3 - print(4 < 5)"));
+
+        assert_eq!(format_expression(&folded), "x + 6 - print(1)");
+    }
+
+    #[test]
+    fn fold_comparison_chain() {
+        assert_eq!(
+            format_expression(&fold_expression(parse("1 < 2 < 1 + 2"))),
+            "1"
+        );
+        assert_eq!(format_expression(&fold_expression(parse("1 < 3 < 2"))), "0");
+        assert_eq!(
+            format_expression(&fold_expression(parse("1 < x < 1 + 2"))),
+            "1 < x < 3"
+        );
+    }
+
+    #[test]
+    fn overflow_is_not_folded() {
+        let expression = parse(&format!("{} + 1", Num::MAX));
+
+        assert_eq!(fold_expression(expression.clone()), expression);
+    }
+
+    #[test]
+    fn overflow_errors_like_unoptimized() {
+        // `0 * 1` is folded, the overflowing operation around it is not
+        let source = format!("var x = 1;\nvar y = ({} + 1) * 2 + 0 * 1;", Num::MAX);
+        let program = Parser::new(SimpleTokenizer::new(&source)).parse().unwrap();
+        let folded = fold_constants(program.clone());
+
+        let unoptimized = Evaluator::new().evaluate(program).unwrap_err();
+        let optimized = Evaluator::new().evaluate(folded).unwrap_err();
+
+        assert_eq!(optimized, unoptimized);
+        assert_eq!(
+            optimized.message,
+            format!("Integer overflow: {} + 1", Num::MAX)
+        );
+    }
+}
@@ -1,69 +1,155 @@
 use crate::{
     lexer::{operator_precedence, Operator, Token, TokenNode, Tokenizer},
-    utils::{Span, SpanError},
+    symbol::Symbol,
+    utils::{Num, Span, SpanError},
 };
+use serde::Serialize;
+use std::fmt;
 
 pub struct Parser<T: Tokenizer> {
     tokenizer: T,
+    optional_semicolons: bool,
+    // Arguments allowed in a single call
+    max_arguments: usize,
+    // Set by `parse_partial`, broken statements become error nodes instead of stopping the parser
+    recovering: bool,
+    errors: Vec<ParserError>,
 }
 
+pub const DEFAULT_MAX_ARGUMENTS: usize = 255;
+
 pub type ParserError = SpanError;
 
-#[derive(PartialEq, Eq, Debug, Clone)]
+#[derive(PartialEq, Eq, Debug, Clone, Serialize)]
 pub struct AstNode<T> {
     pub node: T,
     pub span: Span,
 }
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize)]
 pub enum Expression {
-    Number(i32),
+    Number(Num),
+    Bool(bool),
     BinaryOperator(Box<AstNode<Expression>>, Operator, Box<AstNode<Expression>>),
-    Identifier(String),
+    Identifier(Symbol),
     Call(AstNode<FunctionCall>),
+    // `x = 1` inside an expression, evaluates to the assigned value
+    Assign(Symbol, Box<AstNode<Expression>>),
+    // `condition ? then : else`, only the taken branch is evaluated
+    Conditional(
+        Box<AstNode<Expression>>,
+        Box<AstNode<Expression>>,
+        Box<AstNode<Expression>>,
+    ),
+    // `a < b < c`, true when every neighbouring pair compares true. Every operand is evaluated
+    // at most once and the chain stops at the first false comparison.
+    Comparison(
+        Box<AstNode<Expression>>,
+        Vec<(Operator, AstNode<Expression>)>,
+    ),
+    // Placeholder for an expression that could not be parsed, see `Parser::parse_partial`
+    Error,
 }
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize)]
 pub struct Program {
     pub lines: Vec<AstNode<Line>>,
 }
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize)]
 pub enum Line {
-    Assignment(String, AstNode<Expression>),
-    Reassignment(String, AstNode<Expression>),
+    Assignment(Symbol, AstNode<Expression>),
+    // `var a, b = 1, 2;`, one value for every name. All values are evaluated before the first
+    // variable is defined, so `var a, b = b, a;` with outer `a` and `b` swaps them.
+    MultiAssignment(Vec<Symbol>, Vec<AstNode<Expression>>),
+    // `const x = 1;`, a variable that can not be reassigned
+    ConstAssignment(Symbol, AstNode<Expression>),
+    Reassignment(Symbol, AstNode<Expression>),
     Call(AstNode<FunctionCall>),
+    // `while condition { }`. The condition is evaluated exactly once before every iteration and
+    // once more when it ends the loop, so its side effects, like `input()`, happen that often.
     Loop(AstNode<Expression>, Vec<AstNode<Line>>),
+    // `do { } while (condition);`, the body runs once before the condition is checked
+    DoWhile(Vec<AstNode<Line>>, AstNode<Expression>),
+    // `match x { 1 => { } _ => { } }`, runs the block of the first matching arm. Nothing runs
+    // when no arm matches and there is no wildcard.
+    Match(AstNode<Expression>, Vec<(Pattern, Vec<AstNode<Line>>)>),
+    // `x + 1;` or `input();` inside a larger expression, evaluated for its effects and the value
+    // is dropped. A plain call or assignment is a `Call` or `Reassignment` line instead.
+    Expression(AstNode<Expression>),
+    // `include "lib.txt";`, replaced by the lines of the file when loaded with `include::Loader`
+    Include(Symbol),
+    // Placeholder for a statement that could not be parsed, see `Parser::parse_partial`
+    Error,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize)]
+pub enum Pattern {
+    Number(Num),
+    // `_`, matches every value
+    Wildcard,
+}
+
+impl Pattern {
+    pub fn matches(&self, value: Num) -> bool {
+        match self {
+            Pattern::Number(number) => *number == value,
+            Pattern::Wildcard => true,
+        }
+    }
 }
 
-#[derive(PartialEq, Debug, Clone)]
+impl fmt::Display for Pattern {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pattern::Number(value) => write!(formatter, "{value}"),
+            Pattern::Wildcard => formatter.write_str("_"),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize)]
 pub struct FunctionCall {
-    pub name: String,
+    pub name: Symbol,
     pub arguments: Vec<AstNode<Expression>>,
 }
 
 macro_rules! take_token {
-    ($tokenizer:expr, $pattern:pat) => {
-        $tokenizer.next().and_then(|token_node| match token_node {
-            TokenNode {
-                token: $pattern,
-                span,
-            } => Ok(span),
-            TokenNode { token, span } => Err(ParserError {
-                message: format!(
-                    "Unexpected token {:?}, expected: {}",
-                    token,
-                    stringify!($pattern)
-                ),
-                span,
-            }),
-        })
+    ($tokenizer:expr, $expected:expr) => {
+        // The token is only consumed when it matches, `parse_partial` skips from the wrong one
+        $tokenizer
+            .peek()
+            .and_then(|TokenNode { token, span }| match token {
+                token if token == $expected => $tokenizer.next().map(|_| span),
+                token => Err(ParserError {
+                    message: format!("Unexpected token '{token}', expected '{}'", $expected),
+                    span,
+                }),
+            })
     };
 }
 
 impl<T: Tokenizer> Parser<T> {
     pub fn new(tokenizer: T) -> Parser<T> {
-        Parser { tokenizer }
+        Parser {
+            tokenizer,
+            optional_semicolons: false,
+            max_arguments: DEFAULT_MAX_ARGUMENTS,
+            recovering: false,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn with_max_arguments(mut self, max_arguments: usize) -> Self {
+        self.max_arguments = max_arguments;
+        self
+    }
+
+    // Lets a newline end a statement in place of `;`, the tokenizer has to be
+    // created with `SimpleTokenizer::with_newlines` for this to have an effect
+    pub fn with_optional_semicolons(mut self) -> Self {
+        self.optional_semicolons = true;
+        self
     }
 
     pub fn parse(&mut self) -> Result<AstNode<Program>, ParserError> {
@@ -84,45 +170,159 @@ impl<T: Tokenizer> Parser<T> {
         })
     }
 
+    // Best effort parse for tooling. Statements that fail to parse are skipped up to the next `;`
+    // and replaced with error nodes, so the rest of the program is still available. Returns the
+    // program with every error found, an empty list means the program is valid.
+    pub fn parse_partial(&mut self) -> (AstNode<Program>, Vec<ParserError>) {
+        self.recovering = true;
+
+        let start = self.peek_start();
+        let mut span = Span { start, end: start };
+        let mut lines = Vec::new();
+
+        while !matches!(
+            self.tokenizer.peek(),
+            Ok(TokenNode {
+                token: Token::Eof,
+                ..
+            })
+        ) {
+            let node = self.recover_line(false);
+            span.end = node.span.end;
+            lines.push(node);
+        }
+
+        self.recovering = false;
+
+        let program = AstNode {
+            node: Program { lines },
+            span,
+        };
+        (program, std::mem::take(&mut self.errors))
+    }
+
+    // A single expression with an optional semicolon, used by the REPL
+    pub fn parse_single_expression(&mut self) -> Result<AstNode<Expression>, ParserError> {
+        let expression = self.parse_expression()?;
+
+        if self.tokenizer.peek()? == Token::SemiColon {
+            take_token!(self.tokenizer, Token::SemiColon)?;
+        }
+
+        take_token!(self.tokenizer, Token::Eof)?;
+
+        Ok(expression)
+    }
+
     fn parse_line(&mut self) -> Result<AstNode<Line>, ParserError> {
         match self.tokenizer.peek()?.token {
-            Token::Var => self.parse_assignment(),
+            Token::Var | Token::Const => self.parse_assignment(),
             Token::While => self.parse_loop(),
-            Token::Identifier(_) => self.parse_reassignment_or_call(),
+            Token::Do => self.parse_do_while(),
+            Token::Match => self.parse_match(),
+            Token::Include => self.parse_include(),
+            Token::Identifier(_) => self.parse_identifier_line(),
+            Token::Number(_) | Token::True | Token::False | Token::OpeningParenthesis => {
+                let expression = self.parse_expression()?;
+                self.finish_expression_line(expression)
+            }
             other => Err(ParserError {
-                message: format!("Unexpected token {other:?}, expected: Var, While, Identifier"),
+                message: format!(
+                    "Unexpected token '{other}', expected: var, const, while, do, match, include, expression"
+                ),
                 span: self.tokenizer.peek()?.span,
             }),
         }
     }
 
     fn parse_assignment(&mut self) -> Result<AstNode<Line>, ParserError> {
-        let var_span = take_token!(self.tokenizer, Token::Var)?;
+        let constant = self.tokenizer.peek()? == Token::Const;
+        let var_span = if constant {
+            take_token!(self.tokenizer, Token::Const)?
+        } else {
+            take_token!(self.tokenizer, Token::Var)?
+        };
+
+        let identifier = self.parse_declared_name()?;
 
-        let identifier = match self.tokenizer.next()? {
+        if !constant && self.tokenizer.peek()? == Token::Comma {
+            return self.parse_multi_assignment(var_span, identifier);
+        }
+
+        take_token!(self.tokenizer, Token::Equals)?;
+
+        let expression = self.parse_statement_expression()?;
+
+        let end = self.parse_statement_end(expression.span.end)?;
+
+        Ok(AstNode {
+            node: if constant {
+                Line::ConstAssignment(identifier, expression)
+            } else {
+                Line::Assignment(identifier, expression)
+            },
+            span: Span {
+                start: var_span.start,
+                end,
+            },
+        })
+    }
+
+    fn parse_declared_name(&mut self) -> Result<Symbol, ParserError> {
+        match self.tokenizer.peek()? {
             TokenNode {
                 token: Token::Identifier(name),
                 span: _,
-            } => name,
-            TokenNode { token, span } => {
-                return Err(ParserError {
-                    message: format!("Unexpected token {token:?}, expected: Identifier"),
-                    span,
-                })
+            } => {
+                self.tokenizer.next()?;
+                Ok(name)
             }
-        };
+            TokenNode { token, span } => Err(ParserError {
+                message: format!("Unexpected token '{token}', expected: identifier"),
+                span,
+            }),
+        }
+    }
+
+    // The rest of `var a, b = 1, 2;` after the first name
+    fn parse_multi_assignment(
+        &mut self,
+        var_span: Span,
+        first: Symbol,
+    ) -> Result<AstNode<Line>, ParserError> {
+        let mut names = vec![first];
+        while self.tokenizer.peek()? == Token::Comma {
+            take_token!(self.tokenizer, Token::Comma)?;
+            names.push(self.parse_declared_name()?);
+        }
 
         take_token!(self.tokenizer, Token::Equals)?;
 
-        let expression = self.parse_expression()?;
+        let mut values = vec![self.parse_statement_expression()?];
+        while self.tokenizer.peek()? == Token::Comma {
+            take_token!(self.tokenizer, Token::Comma)?;
+            values.push(self.parse_statement_expression()?);
+        }
 
-        let semicolon_span = take_token!(self.tokenizer, Token::SemiColon)?;
+        let values_span = values[0].span.merge(&values[values.len() - 1].span);
+        if names.len() != values.len() {
+            return Err(ParserError {
+                message: format!(
+                    "Expected {} values, one for every variable, got {}",
+                    names.len(),
+                    values.len()
+                ),
+                span: values_span,
+            });
+        }
+
+        let end = self.parse_statement_end(values_span.end)?;
 
         Ok(AstNode {
-            node: Line::Assignment(identifier, expression),
+            node: Line::MultiAssignment(names, values),
             span: Span {
                 start: var_span.start,
-                end: semicolon_span.end,
+                end,
             },
         })
     }
@@ -132,26 +332,129 @@ impl<T: Tokenizer> Parser<T> {
 
         let condition = self.parse_expression()?;
 
+        let (lines, close_span) = self.parse_block()?;
+
+        Ok(AstNode {
+            node: Line::Loop(condition, lines),
+            span: while_span.to(close_span),
+        })
+    }
+
+    // The condition needs parentheses and the `;`, so the `while` can not be read as the start
+    // of a new loop
+    fn parse_do_while(&mut self) -> Result<AstNode<Line>, ParserError> {
+        let do_span = take_token!(self.tokenizer, Token::Do)?;
+
+        let (lines, _) = self.parse_block()?;
+
+        take_token!(self.tokenizer, Token::While)?;
+        take_token!(self.tokenizer, Token::OpeningParenthesis)?;
+        let condition = self.parse_expression()?;
+        let close_span = take_token!(self.tokenizer, Token::ClosingParenthesis)?;
+
+        let end = self.parse_statement_end(close_span.end)?;
+
+        Ok(AstNode {
+            node: Line::DoWhile(lines, condition),
+            span: Span {
+                start: do_span.start,
+                end,
+            },
+        })
+    }
+
+    fn parse_match(&mut self) -> Result<AstNode<Line>, ParserError> {
+        let match_span = take_token!(self.tokenizer, Token::Match)?;
+
+        let scrutinee = self.parse_expression()?;
+
+        take_token!(self.tokenizer, Token::OpeningCurlyBracket)?;
+
+        let mut arms = Vec::new();
+        while self.tokenizer.peek()? != Token::ClosingCurlyBracket {
+            let pattern = match self.tokenizer.next()? {
+                TokenNode {
+                    token: Token::Number(value),
+                    ..
+                } => Pattern::Number(value),
+                TokenNode {
+                    token: Token::Underscore,
+                    ..
+                } => Pattern::Wildcard,
+                TokenNode { token, span } => {
+                    return Err(ParserError {
+                        message: format!("Unexpected token '{token}', expected: number, _, }}"),
+                        span,
+                    })
+                }
+            };
+
+            take_token!(self.tokenizer, Token::FatArrow)?;
+            let (lines, _) = self.parse_block()?;
+            arms.push((pattern, lines));
+        }
+
+        let close_span = take_token!(self.tokenizer, Token::ClosingCurlyBracket)?;
+
+        Ok(AstNode {
+            node: Line::Match(scrutinee, arms),
+            span: match_span.to(close_span),
+        })
+    }
+
+    // Lines between curly brackets, with the span of the closing bracket
+    fn parse_block(&mut self) -> Result<(Vec<AstNode<Line>>, Span), ParserError> {
         take_token!(self.tokenizer, Token::OpeningCurlyBracket)?;
 
         let mut lines = Vec::new();
 
-        while self.tokenizer.peek()?.token != Token::ClosingCurlyBracket {
-            lines.push(self.parse_line()?);
+        while !matches!(
+            self.tokenizer.peek()?.token,
+            Token::ClosingCurlyBracket | Token::Eof
+        ) {
+            lines.push(if self.recovering {
+                self.recover_line(true)
+            } else {
+                self.parse_line()?
+            });
         }
 
         let close_span = take_token!(self.tokenizer, Token::ClosingCurlyBracket)?;
 
+        Ok((lines, close_span))
+    }
+
+    fn parse_include(&mut self) -> Result<AstNode<Line>, ParserError> {
+        let include_span = take_token!(self.tokenizer, Token::Include)?;
+
+        let (path, path_span) = match self.tokenizer.peek()? {
+            TokenNode {
+                token: Token::StringLiteral(path),
+                span,
+            } => {
+                self.tokenizer.next()?;
+                (path, span)
+            }
+            TokenNode { token, span } => {
+                return Err(ParserError {
+                    message: format!("Unexpected token '{token}', expected: file path"),
+                    span,
+                })
+            }
+        };
+
+        let end = self.parse_statement_end(path_span.end)?;
+
         Ok(AstNode {
-            node: Line::Loop(condition, lines),
+            node: Line::Include(path),
             span: Span {
-                start: while_span.start,
-                end: close_span.end,
+                start: include_span.start,
+                end,
             },
         })
     }
 
-    fn parse_reassignment_or_call(&mut self) -> Result<AstNode<Line>, ParserError> {
+    fn parse_identifier_line(&mut self) -> Result<AstNode<Line>, ParserError> {
         let (identifier, identifier_span) = match self.tokenizer.next()? {
             TokenNode {
                 token: Token::Identifier(name),
@@ -159,66 +462,258 @@ impl<T: Tokenizer> Parser<T> {
             } => (name, span),
             TokenNode { token, span } => {
                 return Err(ParserError {
-                    message: format!("Unexpected token {token:?}, expected: Identifier"),
+                    message: format!("Unexpected token '{token}', expected: identifier"),
                     span,
                 })
             }
         };
 
-        if self.tokenizer.peek()? == Token::Equals {
+        let next = self.tokenizer.peek()?;
+        if next == Token::Equals {
             // Reassignment
 
             take_token!(self.tokenizer, Token::Equals)?;
 
-            let expression = self.parse_expression()?;
+            let expression = self.parse_statement_expression()?;
 
-            let semicolon_span = take_token!(self.tokenizer, Token::SemiColon)?;
+            let end = self.parse_statement_end(expression.span.end)?;
 
             Ok(AstNode {
                 node: Line::Reassignment(identifier, expression),
                 span: Span {
                     start: identifier_span.start,
-                    end: semicolon_span.end,
+                    end,
+                },
+            })
+        } else if let Token::CompoundAssignment(operator) = next.token {
+            // `x += 1` is the same as `x = x + 1`
+
+            take_token!(self.tokenizer, Token::CompoundAssignment(operator))?;
+
+            let right = self.parse_statement_expression()?;
+            let expression_span = identifier_span.to(right.span);
+            let expression = AstNode {
+                node: Expression::BinaryOperator(
+                    Box::new(AstNode {
+                        node: Expression::Identifier(identifier),
+                        span: identifier_span,
+                    }),
+                    operator,
+                    Box::new(right),
+                ),
+                span: expression_span,
+            };
+
+            let end = self.parse_statement_end(expression_span.end)?;
+
+            Ok(AstNode {
+                node: Line::Reassignment(identifier, expression),
+                span: Span {
+                    start: identifier_span.start,
+                    end,
                 },
             })
         } else {
-            // Call
+            // A call or an expression statement starting with the identifier
+            let primary = self.parse_identifier_expression(identifier, identifier_span)?;
+            let expression = self.parse_operators(primary, 0)?;
+            let expression = self.parse_assignment_or_conditional(expression)?;
+
+            // Nothing followed a lone identifier, like `x 5;`, so point at what could have
+            if let Expression::Identifier(_) = expression.node {
+                let TokenNode { token, span } = self.tokenizer.peek()?;
+                if !self.ends_statement(&token) {
+                    return Err(ParserError {
+                        message: format!(
+                            "Unexpected token '{token}', expected ';', an operator, `=` or `(`"
+                        ),
+                        span,
+                    });
+                }
+            }
+
+            self.finish_expression_line(expression)
+        }
+    }
 
-            take_token!(self.tokenizer, Token::OpeningParenthesis)?;
+    fn finish_expression_line(
+        &mut self,
+        expression: AstNode<Expression>,
+    ) -> Result<AstNode<Line>, ParserError> {
+        // The span of a call expression is only its name
+        let expression_end = match &expression.node {
+            Expression::Call(call) => call.span.end,
+            _ => expression.span.end,
+        };
+        let end = self.parse_statement_end(expression_end)?;
+        let span = Span {
+            start: expression.span.start,
+            end,
+        };
 
-            let arguments = self.parse_arguments()?;
+        let node = match expression.node {
+            Expression::Call(call) => Line::Call(call),
+            node => Line::Expression(AstNode {
+                node,
+                span: expression.span,
+            }),
+        };
 
-            let close_span = take_token!(self.tokenizer, Token::ClosingParenthesis)?;
+        Ok(AstNode { node, span })
+    }
 
-            let semicolon_span = take_token!(self.tokenizer, Token::SemiColon)?;
+    fn peek_start(&mut self) -> usize {
+        match self.tokenizer.peek() {
+            Ok(token) => token.span.start,
+            Err(error) => error.span.start,
+        }
+    }
 
-            let call = AstNode {
-                node: FunctionCall {
-                    name: identifier,
-                    arguments,
-                },
-                span: Span {
-                    start: identifier_span.start,
-                    end: close_span.end,
-                },
+    // Parses a statement, or records the error and returns an error node covering the skipped
+    // tokens. Inside a block the closing bracket is left for the block.
+    fn recover_line(&mut self, in_block: bool) -> AstNode<Line> {
+        let start = self.peek_start();
+
+        match self.parse_line() {
+            Ok(line) => line,
+            Err(error) => {
+                let end = self.skip_statement(true, in_block).0;
+                let end = end.unwrap_or(error.span.end).max(start);
+                self.errors.push(error);
+                AstNode {
+                    node: Line::Error,
+                    span: Span { start, end },
+                }
+            }
+        }
+    }
+
+    // The value of an assignment. When recovering, a broken expression followed by `;` becomes
+    // an error node, so the statement itself is kept.
+    fn parse_statement_expression(&mut self) -> Result<AstNode<Expression>, ParserError> {
+        if !self.recovering {
+            return self.parse_expression();
+        }
+
+        let start = self.peek_start();
+        match self.parse_expression() {
+            Ok(expression) => Ok(expression),
+            Err(error) => {
+                let (end, at_statement_end) = self.skip_statement(false, true);
+                if !at_statement_end {
+                    return Err(error);
+                }
+                self.errors.push(error);
+                Ok(AstNode {
+                    node: Expression::Error,
+                    span: Span {
+                        start,
+                        end: end.unwrap_or(start),
+                    },
+                })
+            }
+        }
+    }
+
+    // Skips tokens up to the end of the current statement. Returns the end of the last skipped
+    // token and whether a statement end was found. A closing bracket without a matching opening
+    // one is skipped as well, unless it closes the enclosing block.
+    fn skip_statement(&mut self, consume_end: bool, in_block: bool) -> (Option<usize>, bool) {
+        let mut end = None;
+        let mut depth = 0;
+
+        loop {
+            let token = match self.tokenizer.peek() {
+                Ok(token) => token,
+                Err(error) => {
+                    // The tokenizer skips the rest of the input after an error
+                    let _ = self.tokenizer.next();
+                    return (Some(error.span.end), false);
+                }
             };
 
-            Ok(AstNode {
-                node: Line::Call(call),
-                span: Span {
-                    start: identifier_span.start,
-                    end: semicolon_span.end,
-                },
-            })
+            match token.token {
+                Token::Eof => return (end, false),
+                Token::SemiColon | Token::Newline if depth == 0 => {
+                    if consume_end {
+                        let _ = self.tokenizer.next();
+                        end = Some(token.span.end);
+                    }
+                    return (end, true);
+                }
+                Token::OpeningCurlyBracket => depth += 1,
+                Token::ClosingCurlyBracket if depth == 0 => {
+                    if !in_block {
+                        let _ = self.tokenizer.next();
+                        end = Some(token.span.end);
+                    }
+                    return (end, false);
+                }
+                Token::ClosingCurlyBracket => depth -= 1,
+                _ => {}
+            }
+
+            let _ = self.tokenizer.next();
+            end = Some(token.span.end);
+        }
+    }
+
+    // Takes the `;` after a statement and returns where the statement ends. With optional
+    // semicolons a newline, a closing curly bracket or the end of input also ends it
+    // Whether `parse_statement_end` accepts the token
+    fn ends_statement(&self, token: &Token) -> bool {
+        match token {
+            Token::SemiColon => true,
+            Token::Newline | Token::ClosingCurlyBracket | Token::Eof => self.optional_semicolons,
+            _ => false,
         }
     }
 
-    fn parse_arguments(&mut self) -> Result<Vec<AstNode<Expression>>, ParserError> {
+    fn parse_statement_end(&mut self, end: usize) -> Result<usize, ParserError> {
+        if self.optional_semicolons {
+            match self.tokenizer.peek()?.token {
+                Token::Newline => {
+                    take_token!(self.tokenizer, Token::Newline)?;
+                    return Ok(end);
+                }
+                Token::ClosingCurlyBracket | Token::Eof => return Ok(end),
+                _ => {}
+            }
+        }
+
+        Ok(take_token!(self.tokenizer, Token::SemiColon)?.end)
+    }
+
+    fn parse_arguments(&mut self, name: &Symbol) -> Result<Vec<AstNode<Expression>>, ParserError> {
         let mut arguments = Vec::new();
 
         while self.tokenizer.peek()? != Token::ClosingParenthesis {
-            arguments.push(self.parse_expression()?);
+            // Names the call, the error of `parse_simple_expression` does not say where it is
+            let TokenNode { token, span } = self.tokenizer.peek()?;
+            if !starts_expression(&token) {
+                return Err(ParserError {
+                    message: format!(
+                        "Unexpected token '{token}', expected expression in argument {} of call to {name}",
+                        arguments.len() + 1
+                    ),
+                    span,
+                });
+            }
+
+            let argument = self.parse_expression()?;
+            if arguments.len() == self.max_arguments {
+                return Err(ParserError {
+                    message: format!(
+                        "Too many arguments, a call can have at most {}",
+                        self.max_arguments
+                    ),
+                    span: argument.span,
+                });
+            }
+            arguments.push(argument);
 
+            // The loop checks for `)` again after the comma, so `print(1, 2,)` is allowed. A second
+            // comma is not an argument, `print(1,,2)` fails at it.
             if self.tokenizer.peek()? != Token::ClosingParenthesis {
                 take_token!(self.tokenizer, Token::Comma)?;
             }
@@ -229,6 +724,16 @@ impl<T: Tokenizer> Parser<T> {
 
     // Expression without operators
     fn parse_simple_expression(&mut self) -> Result<AstNode<Expression>, ParserError> {
+        let TokenNode { token, span } = self.tokenizer.peek()?;
+        if !starts_expression(&token) {
+            return Err(ParserError {
+                message: format!(
+                    "Unexpected token '{token}', expected number, boolean, opening parenthesis, identifier"
+                ),
+                span,
+            });
+        }
+
         match self.tokenizer.next()? {
             TokenNode {
                 token: Token::Number(number),
@@ -237,6 +742,20 @@ impl<T: Tokenizer> Parser<T> {
                 node: Expression::Number(number),
                 span,
             }),
+            TokenNode {
+                token: Token::True,
+                span,
+            } => Ok(AstNode {
+                node: Expression::Bool(true),
+                span,
+            }),
+            TokenNode {
+                token: Token::False,
+                span,
+            } => Ok(AstNode {
+                node: Expression::Bool(false),
+                span,
+            }),
             TokenNode {
                 token: Token::OpeningParenthesis,
                 span: _,
@@ -250,50 +769,97 @@ impl<T: Tokenizer> Parser<T> {
             TokenNode {
                 token: Token::Identifier(name),
                 span,
-            } => {
-                if self.tokenizer.peek()? == Token::OpeningParenthesis {
-                    take_token!(self.tokenizer, Token::OpeningParenthesis)?;
+            } => self.parse_identifier_expression(name, span),
+            TokenNode { token, .. } => unreachable!("checked above: {token}"),
+        }
+    }
 
-                    let arguments = self.parse_arguments()?;
+    // A variable, or a call when the taken identifier is followed by `(`
+    fn parse_identifier_expression(
+        &mut self,
+        name: Symbol,
+        span: Span,
+    ) -> Result<AstNode<Expression>, ParserError> {
+        if self.tokenizer.peek()? != Token::OpeningParenthesis {
+            return Ok(AstNode {
+                node: Expression::Identifier(name),
+                span,
+            });
+        }
 
-                    let close_span = take_token!(self.tokenizer, Token::ClosingParenthesis)?;
+        take_token!(self.tokenizer, Token::OpeningParenthesis)?;
 
-                    Ok(AstNode {
-                        node: Expression::Call(AstNode {
-                            node: FunctionCall { name, arguments },
-                            span: Span {
-                                start: span.start,
-                                end: close_span.end,
-                            },
-                        }),
-                        span,
-                    })
-                } else {
-                    Ok(AstNode {
-                        node: Expression::Identifier(name),
-                        span,
-                    })
-                }
-            }
-            TokenNode { token, span } => Err(ParserError {
-                message: format!(
-                    "Unexpected token {token:?}, expected number, opening parenthesis, identifier"
-                ),
-                span,
+        let arguments = self.parse_arguments(&name)?;
+
+        let close_span = take_token!(self.tokenizer, Token::ClosingParenthesis)?;
+
+        Ok(AstNode {
+            node: Expression::Call(AstNode {
+                node: FunctionCall { name, arguments },
+                span: span.to(close_span),
             }),
-        }
+            span,
+        })
     }
 
     fn parse_expression(&mut self) -> Result<AstNode<Expression>, ParserError> {
-        self.parse_operator_expression(0)
+        let expression = self.parse_operator_expression(0)?;
+        self.parse_assignment_or_conditional(expression)
+    }
+
+    fn parse_assignment_or_conditional(
+        &mut self,
+        expression: AstNode<Expression>,
+    ) -> Result<AstNode<Expression>, ParserError> {
+        // Assignment and the conditional bind loosest and are right associative, `x = y = 1` sets
+        // both to 1 and `a ? b : c ? d : e` is `a ? b : (c ? d : e)`
+        match expression.node {
+            Expression::Identifier(name) if self.tokenizer.peek()? == Token::Equals => {
+                take_token!(self.tokenizer, Token::Equals)?;
+
+                let value = self.parse_expression()?;
+                let span = expression.span.merge(&value.span);
+
+                Ok(AstNode {
+                    node: Expression::Assign(name, Box::new(value)),
+                    span,
+                })
+            }
+            _ if self.tokenizer.peek()? == Token::Question => {
+                take_token!(self.tokenizer, Token::Question)?;
+                let then = self.parse_expression()?;
+                take_token!(self.tokenizer, Token::Colon)?;
+                let otherwise = self.parse_expression()?;
+
+                let span = expression.span.merge(&otherwise.span);
+
+                Ok(AstNode {
+                    node: Expression::Conditional(
+                        Box::new(expression),
+                        Box::new(then),
+                        Box::new(otherwise),
+                    ),
+                    span,
+                })
+            }
+            _ => Ok(expression),
+        }
     }
 
     fn parse_operator_expression(
         &mut self,
         precedence: u8,
     ) -> Result<AstNode<Expression>, ParserError> {
-        let mut left = self.parse_simple_expression()?;
+        let left = self.parse_simple_expression()?;
+        self.parse_operators(left, precedence)
+    }
 
+    // Operators following an already parsed left operand
+    fn parse_operators(
+        &mut self,
+        mut left: AstNode<Expression>,
+        precedence: u8,
+    ) -> Result<AstNode<Expression>, ParserError> {
         while let Token::Operator(operator) = self.tokenizer.peek()?.token {
             let op = operator;
             let next_precedence = operator_precedence(&op);
@@ -301,15 +867,27 @@ impl<T: Tokenizer> Parser<T> {
                 break;
             }
 
-            take_token!(self.tokenizer, Token::Operator(_))?;
-            let right = self.parse_operator_expression(next_precedence)?;
+            take_token!(self.tokenizer, Token::Operator(op))?;
+            // Operators of the same precedence are left associative
+            let right = self.parse_operator_expression(next_precedence + 1)?;
 
-            let result_span = Span {
-                start: left.span.start,
-                end: right.span.end,
-            };
+            let mut comparisons = vec![(op, right)];
+            while let Token::Operator(next) = self.tokenizer.peek()?.token {
+                if !op.is_comparison() || !next.is_comparison() {
+                    break;
+                }
+                take_token!(self.tokenizer, Token::Operator(next))?;
+                comparisons.push((next, self.parse_operator_expression(next_precedence + 1)?));
+            }
+
+            let result_span = left.span.merge(&comparisons[comparisons.len() - 1].1.span);
             left = AstNode {
-                node: Expression::BinaryOperator(Box::new(left), op, Box::new(right)),
+                node: if comparisons.len() == 1 {
+                    let (op, right) = comparisons.remove(0);
+                    Expression::BinaryOperator(Box::new(left), op, Box::new(right))
+                } else {
+                    Expression::Comparison(Box::new(left), comparisons)
+                },
                 span: result_span,
             };
         }
@@ -318,13 +896,28 @@ impl<T: Tokenizer> Parser<T> {
     }
 }
 
+// Tokens `parse_simple_expression` accepts
+fn starts_expression(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Number(_)
+            | Token::True
+            | Token::False
+            | Token::OpeningParenthesis
+            | Token::Identifier(_)
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::VecDeque;
 
     use crate::{
-        lexer::{Operator, Token, TokenNode, TokenResult, Tokenizer, TokenizerError},
-        parser::{AstNode, Expression, Parser},
+        lexer::{
+            Operator, SimpleTokenizer, Token, TokenNode, TokenResult, Tokenizer, TokenizerError,
+        },
+        parser::{AstNode, Expression, Line, Parser, Pattern, Program},
+        sexpr::to_sexpr,
         utils::Span,
     };
 
@@ -347,14 +940,10 @@ mod tests {
         }
 
         fn peek(&mut self) -> TokenResult {
-            let token = self
-                .tokens
-                .front()
-                .ok_or_else(|| TokenizerError {
-                    message: "Empty".into(),
-                    span: Span { start: 0, end: 0 },
-                })?
-                .clone();
+            let token = *self.tokens.front().ok_or_else(|| TokenizerError {
+                message: "Empty".into(),
+                span: Span { start: 0, end: 0 },
+            })?;
 
             Ok(TokenNode {
                 token,
@@ -375,6 +964,17 @@ mod tests {
 
             Ok(result)
         }
+
+        fn collect_spanned_tokens(&mut self) -> Result<Vec<TokenNode>, TokenizerError> {
+            Ok(self
+                .collect_tokens()?
+                .into_iter()
+                .map(|token| TokenNode {
+                    token,
+                    span: Span { start: 0, end: 0 },
+                })
+                .collect())
+        }
     }
 
     fn ast<N>(node: N) -> AstNode<N> {
@@ -398,7 +998,7 @@ mod tests {
             Token::Number(2),
             Token::Eof,
         ]);
-        let mut parser = Parser { tokenizer };
+        let mut parser = Parser::new(tokenizer);
         let exp = parser.parse_expression().unwrap();
         let expected = ast(Expression::BinaryOperator(
             Box::new(ast(Expression::Number(1))),
@@ -419,7 +1019,7 @@ mod tests {
             Token::Number(3),
             Token::Eof,
         ]);
-        let mut parser = Parser { tokenizer };
+        let mut parser = Parser::new(tokenizer);
         let exp = parser.parse_expression().unwrap();
         let expected = ast(Expression::BinaryOperator(
             Box::new(ast(Expression::BinaryOperator(
@@ -434,6 +1034,51 @@ mod tests {
         assert_eq!(exp, expected);
     }
 
+    #[test]
+    fn parse_operator_expression_left_associative() {
+        let tokenizer = tokenizer([
+            Token::Number(1),
+            Token::Operator(Operator::Minus),
+            Token::Number(2),
+            Token::Operator(Operator::Minus),
+            Token::Number(3),
+            Token::Eof,
+        ]);
+        let mut parser = Parser::new(tokenizer);
+        let exp = parser.parse_expression().unwrap();
+        let expected = ast(Expression::BinaryOperator(
+            Box::new(ast(Expression::BinaryOperator(
+                Box::new(ast(Expression::Number(1))),
+                Operator::Minus,
+                Box::new(ast(Expression::Number(2))),
+            ))),
+            Operator::Minus,
+            Box::new(ast(Expression::Number(3))),
+        ));
+
+        assert_eq!(exp, expected);
+    }
+
+    #[test]
+    fn parse_same_precedence_left_associative() {
+        // Every level groups from the left, `a - b + c` is `(a - b) + c`
+        for (source, expected) in [
+            ("1 - 2 + 3", "(+ (- 1 2) 3)"),
+            ("2 * 3 * 4", "(* (* 2 3) 4)"),
+            ("64 >> 2 << 1", "(<< (>> 64 2) 1)"),
+            ("1 & 2 & 3", "(& (& 1 2) 3)"),
+            ("1 ^ 2 ^ 3", "(^ (^ 1 2) 3)"),
+            ("1 | 2 | 3", "(| (| 1 2) 3)"),
+            // Still right of a tighter operator
+            ("1 - 2 * 3 - 4", "(- (- 1 (* 2 3)) 4)"),
+        ] {
+            let expression = Parser::new(SimpleTokenizer::new(source))
+                .parse_single_expression()
+                .unwrap();
+            assert_eq!(to_sexpr(&expression), expected, "{source}");
+        }
+    }
+
     #[test]
     fn parse_operator_expression_precedence_right() {
         let tokenizer = tokenizer([
@@ -444,7 +1089,7 @@ mod tests {
             Token::Number(3),
             Token::Eof,
         ]);
-        let mut parser = Parser { tokenizer };
+        let mut parser = Parser::new(tokenizer);
         let exp = parser.parse_expression().unwrap();
         let expected = ast(Expression::BinaryOperator(
             Box::new(ast(Expression::Number(1))),
@@ -458,4 +1103,626 @@ mod tests {
 
         assert_eq!(exp, expected);
     }
+
+    #[test]
+    fn parse_boolean_literals() {
+        let tokenizer = tokenizer([
+            Token::True,
+            Token::Operator(Operator::LessThan),
+            Token::False,
+            Token::Eof,
+        ]);
+        let mut parser = Parser::new(tokenizer);
+        let exp = parser.parse_expression().unwrap();
+        let expected = ast(Expression::BinaryOperator(
+            Box::new(ast(Expression::Bool(true))),
+            Operator::LessThan,
+            Box::new(ast(Expression::Bool(false))),
+        ));
+
+        assert_eq!(exp, expected);
+    }
+
+    #[test]
+    fn parse_multi_assignment() {
+        let mut parser = Parser::new(SimpleTokenizer::new("var a, b = 1, 2 + 3;"));
+        let program = parser.parse().unwrap();
+
+        assert_eq!(
+            program.node.lines,
+            [AstNode {
+                node: Line::MultiAssignment(
+                    vec!["a".into(), "b".into()],
+                    vec![
+                        AstNode {
+                            node: Expression::Number(1),
+                            span: Span { start: 11, end: 12 },
+                        },
+                        AstNode {
+                            node: Expression::BinaryOperator(
+                                Box::new(AstNode {
+                                    node: Expression::Number(2),
+                                    span: Span { start: 14, end: 15 },
+                                }),
+                                Operator::Plus,
+                                Box::new(AstNode {
+                                    node: Expression::Number(3),
+                                    span: Span { start: 18, end: 19 },
+                                }),
+                            ),
+                            span: Span { start: 14, end: 19 },
+                        },
+                    ],
+                ),
+                span: Span { start: 0, end: 20 },
+            }]
+        );
+    }
+
+    #[test]
+    fn parse_multi_assignment_count_mismatch() {
+        let mut parser = Parser::new(SimpleTokenizer::new("var a, b = 1, 2, 3;"));
+        let error = parser.parse().unwrap_err();
+
+        assert_eq!(
+            error.message,
+            "Expected 2 values, one for every variable, got 3"
+        );
+        assert_eq!(error.span, Span { start: 11, end: 18 });
+
+        let mut parser = Parser::new(SimpleTokenizer::new("var a, b, c = 1;"));
+        let error = parser.parse().unwrap_err();
+        assert_eq!(
+            error.message,
+            "Expected 3 values, one for every variable, got 1"
+        );
+        assert_eq!(error.span, Span { start: 14, end: 15 });
+
+        // Only `var` declares several names
+        let mut parser = Parser::new(SimpleTokenizer::new("const a, b = 1, 2;"));
+        assert!(parser.parse().is_err());
+    }
+
+    #[test]
+    fn parse_compound_assignment() {
+        let mut parser = Parser::new(SimpleTokenizer::new("x *= 2 + 3;"));
+        let program = parser.parse().unwrap();
+        let span = |start, end| Span { start, end };
+
+        assert_eq!(
+            program.node.lines,
+            [AstNode {
+                node: Line::Reassignment(
+                    "x".into(),
+                    AstNode {
+                        node: Expression::BinaryOperator(
+                            Box::new(AstNode {
+                                node: Expression::Identifier("x".into()),
+                                span: span(0, 1),
+                            }),
+                            Operator::Multiplication,
+                            Box::new(AstNode {
+                                node: Expression::BinaryOperator(
+                                    Box::new(AstNode {
+                                        node: Expression::Number(2),
+                                        span: span(5, 6),
+                                    }),
+                                    Operator::Plus,
+                                    Box::new(AstNode {
+                                        node: Expression::Number(3),
+                                        span: span(9, 10),
+                                    }),
+                                ),
+                                span: span(5, 10),
+                            }),
+                        ),
+                        span: span(0, 10),
+                    }
+                ),
+                span: span(0, 11),
+            }]
+        );
+    }
+
+    #[test]
+    fn parse_assignment_expression() {
+        let mut parser = Parser::new(SimpleTokenizer::new("(x = y = 1 + 2) * 3"));
+        let exp = parser.parse_single_expression().unwrap();
+        let span = |start, end| Span { start, end };
+
+        assert_eq!(
+            exp,
+            AstNode {
+                node: Expression::BinaryOperator(
+                    Box::new(AstNode {
+                        node: Expression::Assign(
+                            "x".into(),
+                            Box::new(AstNode {
+                                node: Expression::Assign(
+                                    "y".into(),
+                                    Box::new(AstNode {
+                                        node: Expression::BinaryOperator(
+                                            Box::new(AstNode {
+                                                node: Expression::Number(1),
+                                                span: span(9, 10),
+                                            }),
+                                            Operator::Plus,
+                                            Box::new(AstNode {
+                                                node: Expression::Number(2),
+                                                span: span(13, 14),
+                                            }),
+                                        ),
+                                        span: span(9, 14),
+                                    }),
+                                ),
+                                span: span(5, 14),
+                            }),
+                        ),
+                        span: span(1, 14),
+                    }),
+                    Operator::Multiplication,
+                    Box::new(AstNode {
+                        node: Expression::Number(3),
+                        span: span(18, 19),
+                    }),
+                ),
+                span: span(1, 19),
+            }
+        );
+
+        let mut parser = Parser::new(SimpleTokenizer::new("1 + x = 2"));
+        assert!(parser.parse_single_expression().is_err());
+    }
+
+    #[test]
+    fn parse_max_arguments() {
+        let mut parser = Parser::new(SimpleTokenizer::new("print(1, 2, 3);")).with_max_arguments(3);
+        assert!(parser.parse().is_ok());
+
+        let mut parser =
+            Parser::new(SimpleTokenizer::new("print(1, 2, 3, 4 + 5);")).with_max_arguments(3);
+        let error = parser.parse().unwrap_err();
+
+        assert_eq!(
+            error.message,
+            "Too many arguments, a call can have at most 3"
+        );
+        assert_eq!(error.span, Span { start: 15, end: 20 });
+    }
+
+    #[test]
+    fn parse_trailing_comma() {
+        let mut parser = Parser::new(SimpleTokenizer::new("print(1, 2,);"));
+        let program = parser.parse().unwrap();
+
+        let Line::Call(call) = &program.node.lines[0].node else {
+            panic!("expected a call");
+        };
+        assert_eq!(call.node.arguments.len(), 2);
+
+        // Across lines, with newlines as statement ends
+        let mut parser =
+            Parser::new(SimpleTokenizer::new("print(\n  1,\n  2,\n)\n").with_newlines())
+                .with_optional_semicolons();
+        assert!(parser.parse().is_ok());
+    }
+
+    #[test]
+    fn parse_double_comma() {
+        let mut parser = Parser::new(SimpleTokenizer::new("print(1,,2);"));
+        let error = parser.parse().unwrap_err();
+
+        assert_eq!(
+            error.message,
+            "Unexpected token ',', expected expression in argument 2 of call to print"
+        );
+        assert_eq!(error.span, Span { start: 8, end: 9 });
+
+        let mut parser = Parser::new(SimpleTokenizer::new("print(,);"));
+        let error = parser.parse().unwrap_err();
+        assert_eq!(error.span, Span { start: 6, end: 7 });
+    }
+
+    #[test]
+    fn parse_malformed_argument() {
+        let mut parser = Parser::new(SimpleTokenizer::new("print(;);"));
+        let error = parser.parse().unwrap_err();
+
+        assert_eq!(
+            error.message,
+            "Unexpected token ';', expected expression in argument 1 of call to print"
+        );
+        assert_eq!(error.span, Span { start: 6, end: 7 });
+
+        // Inside an expression the call is named as well
+        let mut parser = Parser::new(SimpleTokenizer::new("var x = 1 + max(x, {);"));
+        let error = parser.parse().unwrap_err();
+
+        assert_eq!(
+            error.message,
+            "Unexpected token '{', expected expression in argument 2 of call to max"
+        );
+        assert_eq!(error.span, Span { start: 19, end: 20 });
+    }
+
+    #[test]
+    fn parse_empty_program() {
+        let mut parser = Parser::new(SimpleTokenizer::new(""));
+        let program = parser.parse().unwrap();
+        let expected = AstNode {
+            node: Program { lines: vec![] },
+            span: Span { start: 0, end: 0 },
+        };
+
+        assert_eq!(program, expected);
+    }
+
+    #[test]
+    fn parse_whitespace_program() {
+        let mut parser = Parser::new(SimpleTokenizer::new("  \n "));
+        let program = parser.parse().unwrap();
+
+        assert!(program.node.lines.is_empty());
+        assert_eq!(program.span, Span { start: 4, end: 4 });
+    }
+
+    #[test]
+    fn parse_single_statement_span() {
+        let mut parser = Parser::new(SimpleTokenizer::new("  print();"));
+        let program = parser.parse().unwrap();
+
+        assert_eq!(program.node.lines.len(), 1);
+        assert_eq!(program.node.lines[0].span, Span { start: 2, end: 10 });
+        assert_eq!(program.span, Span { start: 2, end: 10 });
+    }
+
+    #[test]
+    fn parse_single_token_is_not_skipped() {
+        let mut parser = Parser::new(SimpleTokenizer::new(";"));
+        let error = parser.parse().unwrap_err();
+        assert_eq!(error.span, Span { start: 0, end: 1 });
+
+        // The identifier is consumed once, the error points right after it
+        let mut parser = Parser::new(SimpleTokenizer::new("x"));
+        let error = parser.parse().unwrap_err();
+        assert_eq!(error.span.start, 1);
+    }
+
+    #[test]
+    fn parse_expression_statements() {
+        let mut parser = Parser::new(SimpleTokenizer::new(
+            "1 + 2; input(); x = 1; x; (x = 2) * 3; f() < 1; x += 1;",
+        ));
+        let lines = parser.parse().unwrap().node.lines;
+        let span = |start, end| Span { start, end };
+
+        assert!(matches!(
+            &lines[0].node,
+            Line::Expression(AstNode {
+                node: Expression::BinaryOperator(_, Operator::Plus, _),
+                span: expression_span,
+            }) if *expression_span == span(0, 5)
+        ));
+        assert_eq!(lines[0].span, span(0, 6));
+        assert!(matches!(&lines[1].node, Line::Call(call) if call.node.name == "input".into()));
+        assert_eq!(lines[1].span, span(7, 15));
+        // A plain assignment stays a reassignment
+        assert!(matches!(
+            &lines[2].node,
+            Line::Reassignment(name, AstNode { node: Expression::Number(1), .. }) if *name == "x".into()
+        ));
+        assert_eq!(lines[2].span, span(16, 22));
+        assert!(matches!(
+            &lines[3].node,
+            Line::Expression(AstNode {
+                node: Expression::Identifier(_),
+                ..
+            })
+        ));
+        assert!(matches!(
+            &lines[4].node,
+            Line::Expression(AstNode {
+                node: Expression::BinaryOperator(_, Operator::Multiplication, _),
+                ..
+            })
+        ));
+        assert!(matches!(
+            &lines[5].node,
+            Line::Expression(AstNode {
+                node: Expression::BinaryOperator(_, Operator::LessThan, _),
+                ..
+            })
+        ));
+        assert!(matches!(&lines[6].node, Line::Reassignment(..)));
+    }
+
+    #[test]
+    fn parse_empty_bodies() {
+        let mut parser = Parser::new(SimpleTokenizer::new(
+            "while 1 { } while 0 {} do { } while (0); match 1 { 1 => {} }",
+        ));
+        let lines = parser.parse().unwrap().node.lines;
+
+        assert!(matches!(lines[0].node, Line::Loop(_, ref body) if body.is_empty()));
+        assert_eq!(lines[0].span, Span { start: 0, end: 11 });
+        assert!(matches!(lines[1].node, Line::Loop(_, ref body) if body.is_empty()));
+        assert!(matches!(lines[2].node, Line::DoWhile(ref body, _) if body.is_empty()));
+        assert!(matches!(lines[3].node, Line::Match(_, ref arms) if arms[0].1.is_empty()));
+    }
+
+    #[test]
+    fn parse_do_while() {
+        let mut parser = Parser::new(SimpleTokenizer::new("do { x = x + 1; } while (x < 3); x;"));
+        let lines = parser.parse().unwrap().node.lines;
+
+        let Line::DoWhile(ref body, ref condition) = lines[0].node else {
+            panic!("expected a do while loop, got {:?}", lines[0].node);
+        };
+        assert_eq!(body.len(), 1);
+        assert!(matches!(
+            condition.node,
+            Expression::BinaryOperator(_, Operator::LessThan, _)
+        ));
+        assert_eq!(lines[0].span, Span { start: 0, end: 32 });
+        assert_eq!(lines.len(), 2);
+
+        for (source, message) in [
+            ("do { } while x < 3;", "Unexpected token 'x', expected '('"),
+            (
+                "do { } while (x < 3)",
+                "Unexpected token 'end of input', expected ';'",
+            ),
+            (
+                "do { } while (x < 3) { }",
+                "Unexpected token '{', expected ';'",
+            ),
+        ] {
+            let error = Parser::new(SimpleTokenizer::new(source))
+                .parse()
+                .unwrap_err();
+            assert_eq!(error.message, message);
+        }
+    }
+
+    #[test]
+    fn parse_match() {
+        let mut parser = Parser::new(SimpleTokenizer::new(
+            "match x + 1 { 1 => { y = 1; } 2 => { } _ => { y = 0; y = 2; } }",
+        ));
+        let lines = parser.parse().unwrap().node.lines;
+
+        let Line::Match(ref scrutinee, ref arms) = lines[0].node else {
+            panic!("expected a match, got {:?}", lines[0].node);
+        };
+        assert!(matches!(
+            scrutinee.node,
+            Expression::BinaryOperator(_, Operator::Plus, _)
+        ));
+        let arms: Vec<_> = arms
+            .iter()
+            .map(|(pattern, lines)| (*pattern, lines.len()))
+            .collect();
+        assert_eq!(
+            arms,
+            [
+                (Pattern::Number(1), 1),
+                (Pattern::Number(2), 0),
+                (Pattern::Wildcard, 2)
+            ]
+        );
+        assert_eq!(lines[0].span, Span { start: 0, end: 63 });
+
+        for (source, message) in [
+            (
+                "match x { y => { } }",
+                "Unexpected token 'y', expected: number, _, }",
+            ),
+            ("match x { 1 { } }", "Unexpected token '{', expected '=>'"),
+            ("match x { 1 => y; }", "Unexpected token 'y', expected '{'"),
+        ] {
+            let error = Parser::new(SimpleTokenizer::new(source))
+                .parse()
+                .unwrap_err();
+            assert_eq!(error.message, message);
+        }
+    }
+
+    #[test]
+    fn parse_identifier_without_operator() {
+        let mut parser = Parser::new(SimpleTokenizer::new("x 5;"));
+        let error = parser.parse().unwrap_err();
+
+        assert_eq!(
+            error.message,
+            "Unexpected token '5', expected ';', an operator, `=` or `(`"
+        );
+        assert_eq!(error.span, Span { start: 2, end: 3 });
+
+        // After an operator the expression is only missing its end
+        let mut parser = Parser::new(SimpleTokenizer::new("x + 1 5;"));
+        let error = parser.parse().unwrap_err();
+
+        assert_eq!(error.message, "Unexpected token '5', expected ';'");
+
+        // A lone identifier is a statement of its own
+        let mut parser = Parser::new(SimpleTokenizer::new("x;"));
+        assert!(parser.parse().is_ok());
+    }
+
+    #[test]
+    fn parse_optional_semicolons() {
+        let source = "var x = 1\nprint(x)\nwhile x < 3 {\n    x = x + 1\n}\nprint(x);\n";
+
+        let mut parser =
+            Parser::new(SimpleTokenizer::new(source).with_newlines()).with_optional_semicolons();
+        let program = parser.parse().unwrap();
+
+        assert_eq!(program.node.lines.len(), 4);
+        assert_eq!(program.node.lines[0].span, Span { start: 0, end: 9 });
+        assert_eq!(program.node.lines[1].span, Span { start: 10, end: 18 });
+    }
+
+    #[test]
+    fn parse_strict_semicolons() {
+        let source = "var x = 1\nprint(x)\n";
+
+        let mut parser = Parser::new(SimpleTokenizer::new(source));
+        let error = parser.parse().unwrap_err();
+        assert_eq!(error.message, "Unexpected token 'print', expected ';'");
+
+        let mut parser = Parser::new(SimpleTokenizer::new(source).with_newlines());
+        let error = parser.parse().unwrap_err();
+        assert_eq!(error.message, "Unexpected token 'line break', expected ';'");
+    }
+
+    #[test]
+    fn parse_partial() {
+        let source = "var x = 1; x 5; while x < 3 { x = ; print(x); } print(x);";
+        let (program, errors) = Parser::new(SimpleTokenizer::new(source)).parse_partial();
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].span, Span { start: 13, end: 14 });
+
+        let lines = &program.node.lines;
+        assert_eq!(lines.len(), 4);
+        assert!(matches!(lines[0].node, Line::Assignment(..)));
+        assert_eq!(lines[1].node, Line::Error);
+        assert_eq!(lines[1].span, Span { start: 11, end: 15 });
+        assert!(matches!(lines[3].node, Line::Call(..)));
+
+        let Line::Loop(_, ref body) = lines[2].node else {
+            panic!("expected a loop, got {:?}", lines[2].node);
+        };
+        assert_eq!(body.len(), 2);
+        let Line::Reassignment(_, ref value) = body[0].node else {
+            panic!("expected a reassignment, got {:?}", body[0].node);
+        };
+        assert_eq!(value.node, Expression::Error);
+    }
+
+    #[test]
+    fn parse_partial_stray_bracket() {
+        let (program, errors) =
+            Parser::new(SimpleTokenizer::new("} var x = 1; while x {")).parse_partial();
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(program.node.lines.len(), 3);
+        assert_eq!(program.node.lines[0].node, Line::Error);
+        assert!(matches!(program.node.lines[1].node, Line::Assignment(..)));
+        assert_eq!(program.node.lines[2].node, Line::Error);
+    }
+
+    #[test]
+    fn parse_partial_valid_program() {
+        let source = "var x = 1; print(x);";
+        let (program, errors) = Parser::new(SimpleTokenizer::new(source)).parse_partial();
+
+        assert!(errors.is_empty());
+        assert_eq!(
+            program,
+            Parser::new(SimpleTokenizer::new(source)).parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_conditional() {
+        let mut parser = Parser::new(SimpleTokenizer::new("a ? 1 : b ? 2 : 3"));
+        let exp = parser.parse_single_expression().unwrap();
+        let span = |start, end| Span { start, end };
+        let number = |value, start| {
+            Box::new(AstNode {
+                node: Expression::Number(value),
+                span: span(start, start + 1),
+            })
+        };
+        let identifier = |name: &str, start| {
+            Box::new(AstNode {
+                node: Expression::Identifier(name.into()),
+                span: span(start, start + 1),
+            })
+        };
+
+        assert_eq!(
+            exp,
+            AstNode {
+                node: Expression::Conditional(
+                    identifier("a", 0),
+                    number(1, 4),
+                    Box::new(AstNode {
+                        node: Expression::Conditional(
+                            identifier("b", 8),
+                            number(2, 12),
+                            number(3, 16)
+                        ),
+                        span: span(8, 17),
+                    }),
+                ),
+                span: span(0, 17),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_comparison_chain() {
+        let mut parser = Parser::new(SimpleTokenizer::new("1 < 2 < 3"));
+        let exp = parser.parse_single_expression().unwrap();
+        let span = |start, end| Span { start, end };
+        let number = |value, start| AstNode {
+            node: Expression::Number(value),
+            span: span(start, start + 1),
+        };
+
+        assert_eq!(
+            exp,
+            AstNode {
+                node: Expression::Comparison(
+                    Box::new(number(1, 0)),
+                    vec![
+                        (Operator::LessThan, number(2, 4)),
+                        (Operator::LessThan, number(3, 8))
+                    ],
+                ),
+                span: span(0, 9),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_comparison_with_arithmetic() {
+        let mut parser = Parser::new(SimpleTokenizer::new("1 < 2 + 3"));
+        let exp = parser.parse_single_expression().unwrap();
+
+        let Expression::BinaryOperator(left, Operator::LessThan, right) = exp.node else {
+            panic!("expected a single comparison, got {:?}", exp.node);
+        };
+        assert_eq!(left.node, Expression::Number(1));
+        assert!(matches!(
+            right.node,
+            Expression::BinaryOperator(_, Operator::Plus, _)
+        ));
+
+        // Arithmetic binds tighter on both sides of a chain, parentheses end it
+        let mut parser = Parser::new(SimpleTokenizer::new("0 < 1 + 1 < 3 * 3 & (1 < 2) < 3"));
+        let exp = parser.parse_single_expression().unwrap();
+
+        let Expression::BinaryOperator(chain, Operator::BitwiseAnd, right) = exp.node else {
+            panic!("expected `&` at the top, got {:?}", exp.node);
+        };
+        assert!(
+            matches!(&chain.node, Expression::Comparison(_, comparisons) if comparisons.len() == 2)
+        );
+        assert!(matches!(
+            right.node,
+            Expression::BinaryOperator(_, Operator::LessThan, _)
+        ));
+    }
+
+    #[test]
+    fn parse_conditional_without_colon() {
+        let mut parser = Parser::new(SimpleTokenizer::new("a ? 1;"));
+        let error = parser.parse_single_expression().unwrap_err();
+
+        assert_eq!(error.message, "Unexpected token ';', expected ':'");
+        assert_eq!(error.span, Span { start: 5, end: 6 });
+    }
 }
@@ -0,0 +1,75 @@
+use std::io::Cursor;
+
+use crate::{
+    evaluator::{Evaluator, RuntimeError, State},
+    lexer::SimpleTokenizer,
+    lint::{lint_with_functions, Lint, LintWarning},
+    parser::{Parser, ParserError},
+    runtime::SharedBuffer,
+};
+
+pub struct RunOptions {
+    // Lints to check before running, all of them by default
+    pub lints: Vec<Lint>,
+    pub max_steps: Option<u64>,
+    // Read by `input`
+    pub input: String,
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        RunOptions {
+            lints: Lint::ALL.to_vec(),
+            max_steps: None,
+            input: String::new(),
+        }
+    }
+}
+
+pub struct RunReport {
+    pub parse_error: Option<ParserError>,
+    pub warnings: Vec<LintWarning>,
+    // None when the program could not be parsed
+    pub execution: Option<Execution>,
+}
+
+pub struct Execution {
+    pub result: Result<(), RuntimeError>,
+    // Everything the program printed
+    pub output: String,
+    pub state: State<'static>,
+}
+
+// Parses, lints and runs the source, collecting every outcome instead of stopping at the first
+// error. Warnings do not prevent the program from running.
+pub fn analyze_and_run(source: &str, options: RunOptions) -> RunReport {
+    let program = match Parser::new(SimpleTokenizer::new(source)).parse() {
+        Ok(program) => program,
+        Err(error) => {
+            return RunReport {
+                parse_error: Some(error),
+                warnings: Vec::new(),
+                execution: None,
+            }
+        }
+    };
+
+    let output = SharedBuffer::new();
+    let mut state = State::with_io(Cursor::new(options.input), output.clone());
+    let warnings = lint_with_functions(&program, &options.lints, &state.functions);
+    let evaluator = match options.max_steps {
+        Some(max_steps) => Evaluator::new().with_max_steps(max_steps),
+        None => Evaluator::new(),
+    };
+    let result = evaluator.evaluate_with_state(&mut state, program);
+
+    RunReport {
+        parse_error: None,
+        warnings,
+        execution: Some(Execution {
+            result,
+            output: output.contents(),
+            state,
+        }),
+    }
+}
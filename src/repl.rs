@@ -0,0 +1,131 @@
+use std::io::{self, Write};
+
+use crate::{
+    evaluator::{Evaluator, State},
+    lexer::SimpleTokenizer,
+    parser::{AstNode, Line, Parser},
+    utils::{format_error, Num, SpanError},
+};
+
+// Reads from and writes to the streams of `state`, which are shared with `input` and `print`
+pub fn run_repl(state: &mut State) -> Result<(), String> {
+    let evaluator = Evaluator::new();
+    let write_error = |err: io::Error| format!("Can not write to the console: {err}");
+
+    loop {
+        write!(state.output, "> ")
+            .and_then(|_| state.output.flush())
+            .map_err(write_error)?;
+
+        let mut line = String::new();
+        let read = state
+            .input
+            .read_line(&mut line)
+            .map_err(|err| format!("Can not read from the console: {err}"))?;
+
+        // Ctrl-D
+        if read == 0 {
+            return writeln!(state.output).map_err(write_error);
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            continue;
+        }
+
+        state.exit_code = None;
+        let result = evaluate_input(&evaluator, state, line);
+        // `exit()` ends the session
+        if state.exit_code.is_some() {
+            return Ok(());
+        }
+
+        match result {
+            Ok(Some(value)) => writeln!(state.output, "{value}"),
+            Ok(None) => Ok(()),
+            Err(ref err) => writeln!(state.output, "{}", format_error(err, line)),
+        }
+        .map_err(write_error)?;
+    }
+}
+
+// Evaluates a statement, or an expression if the input is not a valid statement. The value of
+// a single expression statement like `x + 1;` is returned as well.
+pub fn evaluate_input(
+    evaluator: &Evaluator,
+    state: &mut State,
+    input: &str,
+) -> Result<Option<Num>, SpanError> {
+    match Parser::new(SimpleTokenizer::new(input)).parse() {
+        Ok(program) => match program.node.lines.as_slice() {
+            [AstNode {
+                node: Line::Expression(expression),
+                ..
+            }] => {
+                let result = evaluator.evaluate_expression(state, expression).map(Some);
+                state.finish(result)
+            }
+            _ => evaluator.evaluate_with_state(state, program).map(|_| None),
+        },
+        Err(statement_error) => {
+            let expression = Parser::new(SimpleTokenizer::new(input))
+                .parse_single_expression()
+                .map_err(|_| statement_error)?;
+
+            let result = evaluator.evaluate_expression(state, &expression).map(Some);
+            state.finish(result)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{
+        evaluator::{Evaluator, State},
+        runtime::SharedBuffer,
+    };
+
+    use super::{evaluate_input, run_repl};
+
+    #[test]
+    fn state_persists_between_inputs() {
+        let evaluator = Evaluator::new();
+        let mut state = State::new();
+
+        assert_eq!(
+            evaluate_input(&evaluator, &mut state, "var x = 2;"),
+            Ok(None)
+        );
+        assert_eq!(evaluate_input(&evaluator, &mut state, "x * 3"), Ok(Some(6)));
+        assert_eq!(
+            evaluate_input(&evaluator, &mut state, "x + 1;"),
+            Ok(Some(3))
+        );
+    }
+
+    #[test]
+    fn errors_do_not_reset_state() {
+        let evaluator = Evaluator::new();
+        let mut state = State::new();
+
+        evaluate_input(&evaluator, &mut state, "var x = 2;").unwrap();
+        assert!(evaluate_input(&evaluator, &mut state, "var x = 3;").is_err());
+        assert!(evaluate_input(&evaluator, &mut state, "x +").is_err());
+        assert_eq!(evaluate_input(&evaluator, &mut state, "x"), Ok(Some(2)));
+    }
+
+    #[test]
+    fn repl_session() {
+        let output = SharedBuffer::new();
+        let mut state = State::with_io(Cursor::new("var x = 2;\n\nx * 3\ny\n"), output.clone());
+
+        run_repl(&mut state).unwrap();
+
+        assert_eq!(
+            output.contents(),
+            "> > > 6\n> Variable y is not defined, on line 1 char 1:\ny\n> \n"
+        );
+    }
+}
@@ -1,36 +1,212 @@
-use std::io;
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::{
-    evaluator::{Evaluator, RuntimeError, State},
+    evaluator::{Evaluator, RuntimeError, State, Stop},
     parser::{AstNode, Expression, FunctionCall},
+    utils::{Num, Span},
 };
 
+// Output handle that can be given to a `State` while keeping access to what was written
+#[derive(Clone, Default)]
+pub struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl SharedBuffer {
+    pub fn new() -> SharedBuffer {
+        SharedBuffer::default()
+    }
+
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).into_owned()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Calls `callback` with every line as soon as it is complete, without the line break. `flush`
+// passes on the rest when the last line did not end with one.
+pub struct LineCallback<F: FnMut(&str)> {
+    callback: F,
+    line: Vec<u8>,
+}
+
+impl<F: FnMut(&str)> LineCallback<F> {
+    pub fn new(callback: F) -> LineCallback<F> {
+        LineCallback {
+            callback,
+            line: Vec::new(),
+        }
+    }
+}
+
+impl<F: FnMut(&str)> Write for LineCallback<F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for byte in buf {
+            if *byte == b'\n' {
+                (self.callback)(&String::from_utf8_lossy(&self.line));
+                self.line.clear();
+            } else {
+                self.line.push(*byte);
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.line.is_empty() {
+            (self.callback)(&String::from_utf8_lossy(&self.line));
+            self.line.clear();
+        }
+        Ok(())
+    }
+}
+
+// Xorshift64* generator used by `rand`, the same seed always gives the same sequence
+#[derive(Clone)]
+pub struct Random(u64);
+
+impl Random {
+    pub fn new(seed: u64) -> Random {
+        // Xorshift gets stuck on zero
+        Random(if seed == 0 {
+            0x9e37_79b9_7f4a_7c15
+        } else {
+            seed
+        })
+    }
+
+    pub fn from_time() -> Random {
+        // `SystemTime::now` panics on wasm32-unknown-unknown, where there is no clock to read
+        if cfg!(all(target_arch = "wasm32", target_os = "unknown")) {
+            return Random::new(0);
+        }
+
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or_default();
+        Random::new(nanos)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+}
+
+fn check_argument_count(
+    name: &str,
+    AstNode {
+        node: function_call,
+        span,
+    }: &AstNode<FunctionCall>,
+    expected: usize,
+) -> Result<(), RuntimeError> {
+    let n = function_call.arguments.len();
+    if n != expected {
+        return Err(RuntimeError {
+            message: format!("Wrong number of arguments for {name}. Expected {expected}, got {n}"),
+            span: *span,
+        });
+    }
+    Ok(())
+}
+
+fn write_text(state: &mut State, span: &Span, text: &str) -> Result<(), RuntimeError> {
+    write!(state.output, "{text}").map_err(|err| RuntimeError {
+        message: format!("Cannot write to the console: {err:?}"),
+        span: *span,
+    })
+}
+
+fn write_line(state: &mut State, span: &Span, line: &str) -> Result<(), RuntimeError> {
+    write_text(state, span, &format!("{line}\n"))
+}
+
+// Evaluates the arguments and formats them with the output radix, separated by spaces
+fn format_arguments(
+    evaluator: &Evaluator,
+    state: &mut State,
+    function_call: &FunctionCall,
+) -> Result<String, Stop> {
+    let values = function_call
+        .arguments
+        .iter()
+        .map(|argument| {
+            let value = evaluator.evaluate_expression(state, argument)?;
+            Ok(format_integer(value, state.output_radix))
+        })
+        .collect::<Result<Vec<String>, Stop>>()?;
+
+    Ok(values.join(" "))
+}
+
 pub fn function_input(
     _: &Evaluator,
-    _: &mut State,
+    state: &mut State,
     AstNode {
         node: function_call,
         span,
     }: &AstNode<FunctionCall>,
-) -> Result<i32, RuntimeError> {
+) -> Result<Num, Stop> {
     if !function_call.arguments.is_empty() {
         return Err(RuntimeError {
             message: "Input function does not take any arguments".into(),
             span: *span,
-        });
+        }
+        .into());
     };
 
-    println!("Input: ");
+    Ok(read_integer(state, span, 10)?)
+}
+
+// Like `input`, but the number is read in the base given as the argument
+pub fn function_input_radix(
+    evaluator: &Evaluator,
+    state: &mut State,
+    ast_node: &AstNode<FunctionCall>,
+) -> Result<Num, Stop> {
+    check_argument_count("input_radix", ast_node, 1)?;
+    let AstNode {
+        node: function_call,
+        span,
+    } = ast_node;
+
+    match evaluator.evaluate_expression(state, &function_call.arguments[0])? {
+        radix @ 2..=36 => Ok(read_integer(state, span, radix as u32)?),
+        radix => Err(RuntimeError {
+            message: format!("Unsupported base {radix}, expected 2 to 36"),
+            span: *span,
+        }
+        .into()),
+    }
+}
+
+fn read_integer(state: &mut State, span: &Span, radix: u32) -> Result<Num, RuntimeError> {
+    write_line(state, span, "Input: ")?;
 
     let mut input = String::new();
-    io::stdin()
+    state
+        .input
         .read_line(&mut input)
         .map_err(|err| RuntimeError {
             message: format!("Cannot read from the console: {err:?}"),
             span: *span,
         })?;
 
-    input.trim().parse::<i32>().map_err(|err| RuntimeError {
+    Num::from_str_radix(input.trim(), radix).map_err(|err| RuntimeError {
         message: format!("Cannot convert string to integer: {input}, {err:?}"),
         span: *span,
     })
@@ -43,24 +219,573 @@ pub fn function_print(
         node: function_call,
         span,
     }: &AstNode<FunctionCall>,
-) -> Result<i32, RuntimeError> {
+) -> Result<Num, Stop> {
     match function_call.arguments.len() {
         0 => {
-            println!("{:?}", state.variables);
+            let variables = state
+                .variables
+                .iter()
+                .map(|(name, value)| {
+                    format!("{name:?}: {}", format_integer(*value, state.output_radix))
+                })
+                .collect::<Vec<String>>()
+                .join(", ");
+            write_line(state, span, &format!("{{{variables}}}"))?;
             Ok(0)
         }
         1 => {
-            let expression = function_call.arguments.get(0).unwrap();
+            let expression = function_call.arguments.first().unwrap();
             let value = evaluator.evaluate_expression(state, expression)?;
-            match expression.node {
-                Expression::Identifier(ref name) => println!("{name} = {value:?}"),
-                _ => println!("Result = {value:?}"),
+            let value = format_integer(value, state.output_radix);
+            let line = match expression.node {
+                Expression::Identifier(ref name) => format!("{name} = {value}"),
+                _ => format!("Result = {value}"),
             };
+            write_line(state, span, &line)?;
+            Ok(0)
+        }
+        _ => {
+            let line = format_arguments(evaluator, state, function_call)?;
+            write_line(state, span, &line)?;
             Ok(0)
         }
-        n => Err(RuntimeError {
-            message: format!("Too many arguments for print. Expected 0 or 1, got {n}"),
+    }
+}
+
+// Like `print` with a single expression, but without the label and the line break
+pub fn function_write(
+    evaluator: &Evaluator,
+    state: &mut State,
+    ast_node: &AstNode<FunctionCall>,
+) -> Result<Num, Stop> {
+    check_argument_count("write", ast_node, 1)?;
+    let AstNode {
+        node: function_call,
+        span,
+    } = ast_node;
+
+    let text = format_arguments(evaluator, state, function_call)?;
+    write_text(state, span, &text)?;
+    Ok(0)
+}
+
+// Prints the single argument in hex whatever the output radix is, `print_hex(255)` prints 0xff
+pub fn function_print_hex(
+    evaluator: &Evaluator,
+    state: &mut State,
+    ast_node: &AstNode<FunctionCall>,
+) -> Result<Num, Stop> {
+    print_with_radix("print_hex", evaluator, state, ast_node, 16)
+}
+
+pub fn function_print_bin(
+    evaluator: &Evaluator,
+    state: &mut State,
+    ast_node: &AstNode<FunctionCall>,
+) -> Result<Num, Stop> {
+    print_with_radix("print_bin", evaluator, state, ast_node, 2)
+}
+
+fn print_with_radix(
+    name: &str,
+    evaluator: &Evaluator,
+    state: &mut State,
+    ast_node: &AstNode<FunctionCall>,
+    radix: u32,
+) -> Result<Num, Stop> {
+    check_argument_count(name, ast_node, 1)?;
+    let AstNode {
+        node: function_call,
+        span,
+    } = ast_node;
+
+    let value = evaluator.evaluate_expression(state, &function_call.arguments[0])?;
+    write_line(state, span, &format_integer(value, radix))?;
+    Ok(0)
+}
+
+pub fn function_set_radix(
+    evaluator: &Evaluator,
+    state: &mut State,
+    ast_node: &AstNode<FunctionCall>,
+) -> Result<Num, Stop> {
+    check_argument_count("set_radix", ast_node, 1)?;
+    let AstNode {
+        node: function_call,
+        span,
+    } = ast_node;
+
+    match evaluator.evaluate_expression(state, &function_call.arguments[0])? {
+        radix @ (2 | 10 | 16) => {
+            state.output_radix = radix as u32;
+            Ok(0)
+        }
+        radix => Err(RuntimeError {
+            message: format!("Unsupported radix {radix}, expected 2, 10 or 16"),
             span: *span,
-        }),
+        }
+        .into()),
+    }
+}
+
+pub fn function_fact(
+    evaluator: &Evaluator,
+    state: &mut State,
+    ast_node: &AstNode<FunctionCall>,
+) -> Result<Num, Stop> {
+    check_argument_count("fact", ast_node, 1)?;
+    let AstNode {
+        node: function_call,
+        span,
+    } = ast_node;
+
+    let n = evaluator.evaluate_expression(state, &function_call.arguments[0])?;
+    if n < 0 {
+        return Err(RuntimeError {
+            message: format!("Factorial of a negative number: {n}"),
+            span: *span,
+        }
+        .into());
+    }
+
+    Ok((1..=n)
+        .try_fold(1, |result: Num, i| result.checked_mul(i))
+        .ok_or_else(|| RuntimeError {
+            message: format!("Factorial of {n} does not fit in an integer"),
+            span: *span,
+        })?)
+}
+
+pub fn function_abs(
+    evaluator: &Evaluator,
+    state: &mut State,
+    ast_node: &AstNode<FunctionCall>,
+) -> Result<Num, Stop> {
+    check_argument_count("abs", ast_node, 1)?;
+    let AstNode {
+        node: function_call,
+        span,
+    } = ast_node;
+
+    let n = evaluator.evaluate_expression(state, &function_call.arguments[0])?;
+    Ok(n.checked_abs().ok_or_else(|| RuntimeError {
+        message: format!("Absolute value of {n} does not fit in an integer"),
+        span: *span,
+    })?)
+}
+
+pub fn function_min(
+    evaluator: &Evaluator,
+    state: &mut State,
+    ast_node: &AstNode<FunctionCall>,
+) -> Result<Num, Stop> {
+    check_argument_count("min", ast_node, 2)?;
+    let arguments = &ast_node.node.arguments;
+
+    let a = evaluator.evaluate_expression(state, &arguments[0])?;
+    let b = evaluator.evaluate_expression(state, &arguments[1])?;
+    Ok(a.min(b))
+}
+
+pub fn function_max(
+    evaluator: &Evaluator,
+    state: &mut State,
+    ast_node: &AstNode<FunctionCall>,
+) -> Result<Num, Stop> {
+    check_argument_count("max", ast_node, 2)?;
+    let arguments = &ast_node.node.arguments;
+
+    let a = evaluator.evaluate_expression(state, &arguments[0])?;
+    let b = evaluator.evaluate_expression(state, &arguments[1])?;
+    Ok(a.max(b))
+}
+
+pub fn function_var_count(
+    _: &Evaluator,
+    state: &mut State,
+    ast_node: &AstNode<FunctionCall>,
+) -> Result<Num, Stop> {
+    check_argument_count("var_count", ast_node, 0)?;
+
+    Ok(state.variables.len() as Num)
+}
+
+// 1 when the variable exists in the current scope or an enclosing one. The argument is not
+// evaluated, so it has to be a bare identifier: `defined(x)`, not `defined(x + 1)`.
+pub fn function_defined(
+    _: &Evaluator,
+    state: &mut State,
+    ast_node: &AstNode<FunctionCall>,
+) -> Result<Num, Stop> {
+    check_argument_count("defined", ast_node, 1)?;
+    let argument = &ast_node.node.arguments[0];
+
+    match argument.node {
+        Expression::Identifier(ref name) => Ok(state.variables.get(name).is_some() as Num),
+        _ => Err(RuntimeError {
+            message: "defined expects a variable name".into(),
+            span: argument.span,
+        }
+        .into()),
+    }
+}
+
+// Fails with the span of the condition when it is zero
+pub fn function_assert(
+    evaluator: &Evaluator,
+    state: &mut State,
+    ast_node: &AstNode<FunctionCall>,
+) -> Result<Num, Stop> {
+    check_argument_count("assert", ast_node, 1)?;
+    let condition = &ast_node.node.arguments[0];
+
+    match evaluator.evaluate_expression(state, condition)? {
+        0 => Err(RuntimeError {
+            message: "Assertion failed".into(),
+            span: condition.span,
+        }
+        .into()),
+        _ => Ok(0),
+    }
+}
+
+// Stops the program. `Stop::Exit` unwinds the evaluation, `State::finish` keeps the code in
+// `state.exit_code` and turns it back into a successful run.
+pub fn function_exit(
+    evaluator: &Evaluator,
+    state: &mut State,
+    AstNode {
+        node: function_call,
+        span,
+    }: &AstNode<FunctionCall>,
+) -> Result<Num, Stop> {
+    let (code, span) = match function_call.arguments.as_slice() {
+        [] => (0, *span),
+        [code] => (evaluator.evaluate_expression(state, code)?, code.span),
+        arguments => {
+            return Err(RuntimeError {
+                message: format!(
+                    "Wrong number of arguments for exit. Expected 0 or 1, got {}",
+                    arguments.len()
+                ),
+                span: *span,
+            }
+            .into())
+        }
+    };
+
+    // Process exit codes are a single byte
+    if !(0..=255).contains(&code) {
+        return Err(RuntimeError {
+            message: format!("Exit code out of range: {code}, expected 0 to 255"),
+            span,
+        }
+        .into());
+    }
+
+    Err(Stop::Exit(code))
+}
+
+// Integer in `[lo, hi)`
+pub fn function_rand(
+    evaluator: &Evaluator,
+    state: &mut State,
+    ast_node: &AstNode<FunctionCall>,
+) -> Result<Num, Stop> {
+    check_argument_count("rand", ast_node, 2)?;
+    let AstNode {
+        node: function_call,
+        span,
+    } = ast_node;
+
+    let lo = evaluator.evaluate_expression(state, &function_call.arguments[0])?;
+    let hi = evaluator.evaluate_expression(state, &function_call.arguments[1])?;
+    if lo >= hi {
+        return Err(RuntimeError {
+            message: format!("Empty range for rand: {lo}..{hi}"),
+            span: *span,
+        }
+        .into());
+    }
+
+    let size = (hi as i128 - lo as i128) as u64;
+    Ok((lo as i128 + (state.random.next_u64() % size) as i128) as Num)
+}
+
+// Negative numbers are printed as a sign followed by the magnitude (-0xff), not in two's complement
+pub fn format_integer(value: Num, radix: u32) -> String {
+    let sign = if value < 0 { "-" } else { "" };
+    let magnitude = value.unsigned_abs();
+
+    match radix {
+        2 => format!("{sign}0b{magnitude:b}"),
+        16 => format!("{sign}0x{magnitude:x}"),
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{
+        evaluator::{Evaluator, RuntimeError, State},
+        lexer::SimpleTokenizer,
+        parser::Parser,
+        run_with_state,
+        utils::{Num, Span},
+    };
+
+    use super::{format_integer, SharedBuffer};
+
+    fn output(source: &str) -> String {
+        let output = SharedBuffer::new();
+        let mut state = State::with_io(Cursor::new(""), output.clone());
+        run_with_state(source, &mut state).unwrap();
+        output.contents()
+    }
+
+    fn evaluate(source: &str) -> Result<Num, RuntimeError> {
+        let expression = Parser::new(SimpleTokenizer::new(source))
+            .parse_single_expression()
+            .unwrap();
+        let mut state = State::new();
+        let result = Evaluator::new().evaluate_expression(&mut state, &expression);
+        state.finish(result)
+    }
+
+    #[test]
+    fn fact() {
+        assert_eq!(evaluate("fact(5)"), Ok(120));
+        assert_eq!(evaluate("fact(0)"), Ok(1));
+        assert_eq!(evaluate("fact(12)"), Ok(479001600));
+    }
+
+    #[test]
+    fn fact_negative() {
+        let error = evaluate("fact(0 - 1)").unwrap_err();
+
+        assert_eq!(error.message, "Factorial of a negative number: -1");
+        assert_eq!(error.span, Span { start: 0, end: 11 });
+    }
+
+    #[test]
+    #[cfg(not(feature = "i64"))]
+    fn fact_overflow() {
+        let error = evaluate("fact(13)").unwrap_err();
+
+        assert_eq!(error.message, "Factorial of 13 does not fit in an integer");
+        assert_eq!(error.span, Span { start: 0, end: 8 });
+    }
+
+    #[test]
+    fn print_multiple_arguments() {
+        assert_eq!(output("print(1, 2);"), "1 2\n");
+        assert_eq!(output("var x = 3; print(x, x * 2, 0 - 1);"), "3 6 -1\n");
+        assert_eq!(
+            output("var x = 3; print(x); print(x + 1);"),
+            "x = 3\nResult = 4\n"
+        );
+    }
+
+    #[test]
+    fn print_assignment_value() {
+        assert_eq!(
+            output("var x = 0; print(x = 5); print(x);"),
+            "Result = 5\nx = 5\n"
+        );
+        assert_eq!(
+            output("var a = 1; var b = 1; print(a = b = 0, a, b);"),
+            "0 0 0\n"
+        );
+    }
+
+    #[test]
+    fn print_multiple_arguments_error() {
+        let error = run_with_state("print(1, y);", &mut State::new()).unwrap_err();
+
+        assert_eq!(error.message, "Variable y is not defined");
+        assert_eq!(error.span, Span { start: 9, end: 10 });
+    }
+
+    #[test]
+    fn input_radix() {
+        let read = |source: &str, input: &'static str| {
+            let mut state = State::with_io(Cursor::new(input), SharedBuffer::new());
+            run_with_state(source, &mut state).map(|_| state.variable("x"))
+        };
+
+        assert_eq!(read("var x = input_radix(16);", "ff\n"), Ok(Some(255)));
+        assert_eq!(read("var x = input_radix(2);", "-101\n"), Ok(Some(-5)));
+        assert_eq!(read("var x = input();", "10\n"), Ok(Some(10)));
+
+        let error = read("var x = input_radix(2);", "102\n").unwrap_err();
+        assert!(error
+            .message
+            .starts_with("Cannot convert string to integer: 102"));
+        assert_eq!(error.span, Span { start: 8, end: 22 });
+
+        let error = read("var x = input_radix(37);", "1\n").unwrap_err();
+        assert_eq!(error.message, "Unsupported base 37, expected 2 to 36");
+    }
+
+    #[test]
+    fn write_without_line_break() {
+        assert_eq!(output("write(1); write(2);"), "12");
+        assert_eq!(
+            output("set_radix(16); write(255); print(1);"),
+            "0xffResult = 0x1\n"
+        );
+    }
+
+    #[test]
+    fn print_hex_and_bin() {
+        assert_eq!(output("print_hex(255);"), "0xff\n");
+        assert_eq!(output("print_bin(5);"), "0b101\n");
+        // Sign and magnitude, not two's complement
+        assert_eq!(
+            output("print_hex(0 - 255); print_bin(0 - 5);"),
+            "-0xff\n-0b101\n"
+        );
+        // Independent of the output radix
+        assert_eq!(output("set_radix(2); print_hex(16);"), "0x10\n");
+
+        let error = evaluate("print_hex(1, 2)").unwrap_err();
+        assert_eq!(
+            error.message,
+            "Wrong number of arguments for print_hex. Expected 1, got 2"
+        );
+    }
+
+    #[test]
+    fn abs_min_max() {
+        assert_eq!(evaluate("abs(0 - 5)"), Ok(5));
+        assert_eq!(evaluate("abs(5)"), Ok(5));
+        assert_eq!(evaluate("min(3, 7)"), Ok(3));
+        assert_eq!(evaluate("max(3, 7)"), Ok(7));
+        assert_eq!(evaluate("max(0 - 3, min(1, 2))"), Ok(1));
+    }
+
+    #[test]
+    fn abs_errors() {
+        let error = evaluate("abs()").unwrap_err();
+
+        assert_eq!(
+            error.message,
+            "Wrong number of arguments for abs. Expected 1, got 0"
+        );
+        assert_eq!(error.span, Span { start: 0, end: 5 });
+
+        let error = evaluate(&format!("abs(0 - {} - 1)", Num::MAX)).unwrap_err();
+
+        assert_eq!(
+            error.message,
+            format!("Absolute value of {} does not fit in an integer", Num::MIN)
+        );
+    }
+
+    #[test]
+    fn var_count() {
+        let mut state = State::new();
+        run_with_state(
+            "var start = var_count(); var a = 1; var b = 2; var c = var_count();",
+            &mut state,
+        )
+        .unwrap();
+
+        assert_eq!(state.variable("start"), Some(0));
+        assert_eq!(state.variable("c"), Some(3));
+
+        let error = evaluate("var_count(1)").unwrap_err();
+
+        assert_eq!(
+            error.message,
+            "Wrong number of arguments for var_count. Expected 0, got 1"
+        );
+    }
+
+    #[test]
+    fn defined() {
+        let mut state = State::new();
+        run_with_state(
+            "var x = 1; var a = defined(x); var b = defined(y); \
+             var c = 0; while c < 1 { var inner = 1; c = defined(x) + defined(inner); } \
+             var d = defined(inner);",
+            &mut state,
+        )
+        .unwrap();
+
+        assert_eq!(state.variable("a"), Some(1));
+        assert_eq!(state.variable("b"), Some(0));
+        assert_eq!(state.variable("c"), Some(2));
+        // Gone with the loop scope
+        assert_eq!(state.variable("d"), Some(0));
+
+        let error = evaluate("defined(x + 1)").unwrap_err();
+        assert_eq!(error.message, "defined expects a variable name");
+        assert_eq!(error.span, Span { start: 8, end: 13 });
+    }
+
+    #[test]
+    fn assert_condition() {
+        assert_eq!(evaluate("assert(1 < 2)"), Ok(0));
+        assert_eq!(evaluate("assert(true)"), Ok(0));
+
+        let error = evaluate("assert(2 < 1)").unwrap_err();
+
+        assert_eq!(error.message, "Assertion failed");
+        assert_eq!(error.span, Span { start: 7, end: 12 });
+    }
+
+    #[test]
+    fn rand_is_reproducible() {
+        let source = "var i = 0; while i < 100 { print(rand(0 - 3, 4)); i = i + 1; }";
+        let seeded = |seed| {
+            let output = SharedBuffer::new();
+            let mut state = State::with_io(Cursor::new(""), output.clone());
+            state.set_seed(seed);
+            run_with_state(source, &mut state).unwrap();
+            output.contents()
+        };
+
+        let numbers = seeded(42);
+        assert_eq!(numbers, seeded(42));
+        assert_ne!(numbers, seeded(43));
+
+        let numbers = numbers
+            .lines()
+            .map(|line| line.trim_start_matches("Result = ").parse::<Num>().unwrap());
+        assert!(numbers.clone().all(|n| (-3..4).contains(&n)));
+        assert_eq!(numbers.count(), 100);
+    }
+
+    #[test]
+    fn rand_empty_range() {
+        let error = evaluate("rand(2, 2)").unwrap_err();
+
+        assert_eq!(error.message, "Empty range for rand: 2..2");
+        assert_eq!(error.span, Span { start: 0, end: 10 });
+        assert!(evaluate("rand(3, 1)").is_err());
+        assert_eq!(evaluate("rand(5, 6)"), Ok(5));
+    }
+
+    #[test]
+    fn format_hexadecimal() {
+        assert_eq!(format_integer(255, 16), "0xff");
+        assert_eq!(format_integer(-255, 16), "-0xff");
+    }
+
+    #[test]
+    fn format_binary() {
+        assert_eq!(format_integer(10, 2), "0b1010");
+        assert_eq!(
+            format_integer(Num::MIN, 2),
+            format!("-0b1{}", "0".repeat(Num::BITS as usize - 1))
+        );
+    }
+
+    #[test]
+    fn format_decimal() {
+        assert_eq!(format_integer(-42, 10), "-42");
     }
 }
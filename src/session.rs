@@ -0,0 +1,111 @@
+use std::{
+    cell::RefCell,
+    fs,
+    io::{self, BufRead, Cursor, Read},
+    path::Path,
+    rc::Rc,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{evaluator::State, run_with_state, runtime::SharedBuffer, utils::SpanError};
+
+// Everything needed to reproduce a run: the program, the lines it read and what it printed
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub struct Session {
+    pub source: String,
+    pub inputs: Vec<String>,
+    pub output: String,
+}
+
+impl Session {
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, json)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Session> {
+        let json = fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(io::Error::other)
+    }
+}
+
+// Passes reads through, keeping a copy of every byte the program consumed
+struct RecordingReader<R> {
+    inner: R,
+    consumed: Rc<RefCell<Vec<u8>>>,
+}
+
+impl<R: BufRead> Read for RecordingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.consumed.borrow_mut().extend_from_slice(&buf[..read]);
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for RecordingReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        if let Ok(buffer) = self.inner.fill_buf() {
+            let amount = amount.min(buffer.len());
+            self.consumed
+                .borrow_mut()
+                .extend_from_slice(&buffer[..amount]);
+        }
+        self.inner.consume(amount);
+    }
+}
+
+// Runs the program with the given input and records the session. The result of the run is
+// returned as well, a failing program can be recorded just like a working one.
+pub fn record_session(
+    source: &str,
+    input: impl BufRead + 'static,
+) -> (Session, Result<(), SpanError>) {
+    let consumed = Rc::new(RefCell::new(Vec::new()));
+    let output = SharedBuffer::new();
+    let reader = RecordingReader {
+        inner: input,
+        consumed: consumed.clone(),
+    };
+
+    let mut state = State::with_io(reader, output.clone());
+    let result = run_with_state(source, &mut state);
+
+    let consumed = String::from_utf8_lossy(&consumed.borrow()).into_owned();
+    let session = Session {
+        source: source.into(),
+        inputs: consumed.lines().map(String::from).collect(),
+        output: output.contents(),
+    };
+
+    (session, result)
+}
+
+// Runs the recorded program again with the recorded input. Fails if the output differs.
+pub fn replay_session(session: &Session) -> Result<(), String> {
+    let input: String = session
+        .inputs
+        .iter()
+        .map(|line| format!("{line}\n"))
+        .collect();
+
+    let output = SharedBuffer::new();
+    let mut state = State::with_io(Cursor::new(input), output.clone());
+    // The recording does not keep the error of the program, only the output is compared
+    let _ = run_with_state(&session.source, &mut state);
+
+    let output = output.contents();
+    if output == session.output {
+        Ok(())
+    } else {
+        Err(format!(
+            "Replayed output differs from the recording\nexpected:\n{}\nactual:\n{}",
+            session.output, output
+        ))
+    }
+}
@@ -0,0 +1,171 @@
+use crate::parser::{AstNode, Expression, FunctionCall, Line, Program};
+
+// One S-expression per line, like `(var x (+ 1 (* 2 3)))`
+pub fn program_to_sexpr(AstNode { node: program, .. }: &AstNode<Program>) -> String {
+    program
+        .lines
+        .iter()
+        .map(|line| format!("{}\n", line_to_sexpr(line)))
+        .collect()
+}
+
+pub fn line_to_sexpr(AstNode { node: line, .. }: &AstNode<Line>) -> String {
+    match line {
+        Line::Assignment(name, expression) => format!("(var {name} {})", to_sexpr(expression)),
+        Line::MultiAssignment(names, values) => {
+            let names = names
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<Vec<String>>()
+                .join(" ");
+            let values = values
+                .iter()
+                .map(|value| format!(" {}", to_sexpr(value)))
+                .collect::<String>();
+            format!("(var ({names}){values})")
+        }
+        Line::ConstAssignment(name, expression) => {
+            format!("(const {name} {})", to_sexpr(expression))
+        }
+        Line::Reassignment(name, expression) => format!("(set {name} {})", to_sexpr(expression)),
+        Line::Call(function_call) => function_call_to_sexpr(function_call),
+        Line::Expression(expression) => to_sexpr(expression),
+        Line::Loop(condition, lines) => {
+            let body = lines
+                .iter()
+                .map(|line| format!(" {}", line_to_sexpr(line)))
+                .collect::<String>();
+
+            format!("(while {}{body})", to_sexpr(condition))
+        }
+        Line::Match(scrutinee, arms) => {
+            let arms = arms
+                .iter()
+                .map(|(pattern, lines)| {
+                    let body = lines
+                        .iter()
+                        .map(|line| format!(" {}", line_to_sexpr(line)))
+                        .collect::<String>();
+                    format!(" ({pattern}{body})")
+                })
+                .collect::<String>();
+
+            format!("(match {}{arms})", to_sexpr(scrutinee))
+        }
+        Line::DoWhile(lines, condition) => {
+            let body = lines
+                .iter()
+                .map(|line| format!(" {}", line_to_sexpr(line)))
+                .collect::<String>();
+
+            format!("(do-while {}{body})", to_sexpr(condition))
+        }
+        Line::Include(path) => format!("(include \"{path}\")"),
+        Line::Error => "(error)".into(),
+    }
+}
+
+// Nesting shows precedence and associativity, `1 + 2 * 3` is `(+ 1 (* 2 3))`
+pub fn to_sexpr(
+    AstNode {
+        node: expression, ..
+    }: &AstNode<Expression>,
+) -> String {
+    match expression {
+        Expression::Number(value) => value.to_string(),
+        Expression::Bool(value) => value.to_string(),
+        Expression::Error => "(error)".into(),
+        Expression::Identifier(name) => name.to_string(),
+        Expression::Call(function_call) => function_call_to_sexpr(function_call),
+        Expression::Assign(name, value) => format!("(set {name} {})", to_sexpr(value)),
+        Expression::Conditional(condition, then, otherwise) => format!(
+            "(? {} {} {})",
+            to_sexpr(condition),
+            to_sexpr(then),
+            to_sexpr(otherwise)
+        ),
+        // `a < b < c` is `(chain a < b < c)`
+        Expression::Comparison(first, comparisons) => {
+            let rest: String = comparisons
+                .iter()
+                .map(|(operator, operand)| format!(" {} {}", operator.symbol(), to_sexpr(operand)))
+                .collect();
+            format!("(chain {}{rest})", to_sexpr(first))
+        }
+        Expression::BinaryOperator(left, operator, right) => {
+            format!(
+                "({} {} {})",
+                operator.symbol(),
+                to_sexpr(left),
+                to_sexpr(right)
+            )
+        }
+    }
+}
+
+fn function_call_to_sexpr(
+    AstNode {
+        node: function_call,
+        ..
+    }: &AstNode<FunctionCall>,
+) -> String {
+    let arguments = function_call
+        .arguments
+        .iter()
+        .map(|argument| format!(" {}", to_sexpr(argument)))
+        .collect::<String>();
+
+    format!("({}{arguments})", function_call.name)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{lexer::SimpleTokenizer, parser::Parser};
+
+    use super::{program_to_sexpr, to_sexpr};
+
+    fn expression(source: &str) -> String {
+        to_sexpr(
+            &Parser::new(SimpleTokenizer::new(source))
+                .parse_single_expression()
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(expression("1 + 2 * 3"), "(+ 1 (* 2 3))");
+        assert_eq!(expression("1 * 2 + 3"), "(+ (* 1 2) 3)");
+        assert_eq!(expression("(1 + 2) * 3"), "(* (+ 1 2) 3)");
+        assert_eq!(expression("x < y + 1"), "(< x (+ y 1))");
+    }
+
+    #[test]
+    fn left_associativity() {
+        assert_eq!(expression("1 - 2 - 3"), "(- (- 1 2) 3)");
+        assert_eq!(expression("1 - (2 - 3)"), "(- 1 (- 2 3))");
+    }
+
+    #[test]
+    fn conditional() {
+        assert_eq!(expression("a < b ? a : b"), "(? (< a b) a b)");
+        assert_eq!(expression("a ? b : c ? d : e"), "(? a b (? c d e))");
+        assert_eq!(expression("a ? b ? c : d : e"), "(? a (? b c d) e)");
+        assert_eq!(expression("x = a ? 1 : 2"), "(set x (? a 1 2))");
+        assert_eq!(expression("(a ? 1 : 2) + 3"), "(+ (? a 1 2) 3)");
+    }
+
+    #[test]
+    fn program() {
+        let program = Parser::new(SimpleTokenizer::new(
+            "var i = input(); while i < 3 { print(i, f()); i = i + 1; }",
+        ))
+        .parse()
+        .unwrap();
+
+        assert_eq!(
+            program_to_sexpr(&program),
+            "(var i (input))\n(while (< i 3) (print i (f)) (set i (+ i 1)))\n"
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use serde::Serialize;
+
+use crate::utils::{line_info, SpanError};
+
+pub struct Source {
+    pub name: String,
+    pub text: String,
+    // Position of the first byte in the concatenated text
+    pub offset: usize,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+// An error with its position resolved to lines and columns, counted from 1 like in
+// `format_error`, for tools that read the output as JSON
+#[derive(PartialEq, Eq, Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub message: String,
+    pub start_line: usize,
+    pub start_col: usize,
+    pub end_line: usize,
+    pub end_col: usize,
+    pub severity: Severity,
+}
+
+// Named sources concatenated into one text, so a single tokenizer and parser can read them
+// while errors are still reported against the source they come from
+#[derive(Default)]
+pub struct SourceMap {
+    sources: Vec<Source>,
+    text: String,
+}
+
+impl SourceMap {
+    pub fn new() -> SourceMap {
+        SourceMap::default()
+    }
+
+    // Appends the source followed by a line break, so tokens never continue into the next
+    // source. Returns the offset of the source in the concatenated text.
+    pub fn add(&mut self, name: &str, text: &str) -> usize {
+        let offset = self.text.len();
+
+        self.sources.push(Source {
+            name: name.into(),
+            text: text.into(),
+            offset,
+        });
+        self.text.push_str(text);
+        self.text.push('\n');
+
+        offset
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn sources(&self) -> &[Source] {
+        &self.sources
+    }
+
+    // The source containing the offset of the concatenated text, offsets past the end belong
+    // to the last source
+    pub fn find(&self, offset: usize) -> Option<&Source> {
+        self.sources
+            .iter()
+            .rev()
+            .find(|source| source.offset <= offset)
+    }
+
+    pub fn diagnostic(&self, error: &SpanError, severity: Severity) -> Diagnostic {
+        let position = |offset: usize| match self.find(offset) {
+            Some(source) => {
+                let local = (offset - source.offset).min(source.text.len());
+                let (line, column, _) = line_info(&source.text, local);
+                (line, column)
+            }
+            None => (1, 1),
+        };
+        let (start_line, start_col) = position(error.span.start);
+        let (end_line, end_col) = position(error.span.end);
+
+        Diagnostic {
+            message: error.message.clone(),
+            start_line,
+            start_col,
+            end_line,
+            end_col,
+            severity,
+        }
+    }
+
+    pub fn format_error(&self, error: &SpanError) -> String {
+        match self.find(error.span.start) {
+            Some(source) => {
+                let local = (error.span.start - source.offset).min(source.text.len());
+                let (line_number, char_number, line) = line_info(&source.text, local);
+                format!(
+                    "{}, in {} on line {} char {}:\n{}",
+                    error.message, source.name, line_number, char_number, line
+                )
+            }
+            None => error.message.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{lexer::SimpleTokenizer, parser::Parser, utils::SpanError};
+
+    use super::{Diagnostic, Severity, SourceMap};
+
+    #[test]
+    fn offsets_map_to_sources() {
+        let mut source_map = SourceMap::new();
+        assert_eq!(source_map.add("first.txt", "var x = 1;\nvar y = 2;"), 0);
+        assert_eq!(source_map.add("second.txt", "print(x);\nx = ;"), 22);
+
+        assert_eq!(source_map.find(21).unwrap().name, "first.txt");
+        assert_eq!(source_map.find(22).unwrap().name, "second.txt");
+        assert_eq!(source_map.find(1000).unwrap().name, "second.txt");
+
+        let error = SpanError::new("Error".into(), 15, 16);
+        assert_eq!(
+            source_map.format_error(&error),
+            "Error, in first.txt on line 2 char 5:\nvar y = 2;"
+        );
+    }
+
+    #[test]
+    fn diagnostic() {
+        let mut source_map = SourceMap::new();
+        source_map.add("first.txt", "var x = 1;");
+        source_map.add("second.txt", "print(x);\nx = 1 / 0;");
+
+        let error = SpanError::new("Division by zero".into(), 25, 30);
+        let diagnostic = source_map.diagnostic(&error, Severity::Error);
+        assert_eq!(
+            diagnostic,
+            Diagnostic {
+                message: "Division by zero".into(),
+                start_line: 2,
+                start_col: 5,
+                end_line: 2,
+                end_col: 10,
+                severity: Severity::Error,
+            }
+        );
+        assert_eq!(
+            serde_json::to_string(&diagnostic).unwrap(),
+            r#"{"message":"Division by zero","start_line":2,"start_col":5,"end_line":2,"end_col":10,"severity":"error"}"#
+        );
+    }
+
+    #[test]
+    fn parser_error_in_second_source() {
+        let mut source_map = SourceMap::new();
+        source_map.add("first.txt", "var x = 1;");
+        source_map.add("second.txt", "print(x);\nx = ;");
+
+        let error = Parser::new(SimpleTokenizer::new(source_map.text()))
+            .parse()
+            .unwrap_err();
+
+        assert_eq!(
+            source_map.format_error(&error),
+            "Unexpected token ';', expected number, boolean, opening parenthesis, identifier, in second.txt on line 2 char 5:\nx = ;"
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use serde::{Serialize, Serializer};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, OnceLock};
+
+// Interned identifier, cheap to copy, hash and compare. Symbols compare by their id, the name is
+// kept next to it so formatting does not need the table.
+#[derive(Clone, Copy)]
+pub struct Symbol {
+    id: u32,
+    name: &'static str,
+}
+
+// The one table behind `Symbol::intern`, ids are only meaningful within it. Names are leaked
+// and never freed, so they live as long as the process, like the symbols referring to them.
+// Every distinct name costs its length once: nothing for a program, but a long-running REPL or
+// host that keeps seeing new names keeps growing.
+#[derive(Default)]
+struct SymbolTable {
+    symbols: HashMap<&'static str, Symbol>,
+}
+
+impl SymbolTable {
+    fn intern(&mut self, name: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(name) {
+            return *symbol;
+        }
+
+        let name: &'static str = Box::leak(name.into());
+        let symbol = Symbol {
+            id: self.symbols.len() as u32,
+            name,
+        };
+        self.symbols.insert(name, symbol);
+
+        symbol
+    }
+}
+
+fn global_table() -> &'static Mutex<SymbolTable> {
+    static TABLE: OnceLock<Mutex<SymbolTable>> = OnceLock::new();
+    TABLE.get_or_init(Default::default)
+}
+
+impl Symbol {
+    pub fn intern(name: &str) -> Symbol {
+        global_table().lock().unwrap().intern(name)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        self.name
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// In the order the names were first interned
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.id.cmp(&other.id)
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        Symbol::intern(name)
+    }
+}
+
+impl Serialize for Symbol {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), formatter)
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), formatter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        evaluator::State,
+        lexer::{SimpleTokenizer, Token, Tokenizer},
+        run_with_state,
+    };
+
+    use super::Symbol;
+
+    #[test]
+    fn interns_once() {
+        // Names no other test uses, so this test interns them first
+        let first = Symbol::intern("interns_once_first");
+        let second = Symbol::intern("interns_once_second");
+
+        assert_eq!(Symbol::intern("interns_once_first"), first);
+        assert_ne!(first, second);
+        assert!(first < second);
+        assert_eq!(first.as_str(), "interns_once_first");
+        assert_eq!(second.to_string(), "interns_once_second");
+    }
+
+    #[test]
+    fn repeated_identifiers_share_symbol() {
+        let tokens = SimpleTokenizer::new("abc = abc + abc;")
+            .collect_tokens()
+            .unwrap();
+        let symbols = tokens
+            .iter()
+            .filter_map(|token| match token {
+                Token::Identifier(symbol) => Some(*symbol),
+                _ => None,
+            })
+            .collect::<Vec<Symbol>>();
+
+        assert_eq!(symbols.len(), 3);
+        assert!(symbols.iter().all(|symbol| *symbol == symbols[0]));
+        assert_eq!(symbols[0].as_str(), "abc");
+    }
+
+    #[test]
+    fn evaluation_with_symbols() {
+        let mut state = State::new();
+        run_with_state("var x = 2; var y = x; x = x * x + y;", &mut state).unwrap();
+
+        assert_eq!(state.variable("x"), Some(6));
+        assert_eq!(state.variable("y"), Some(2));
+        assert_eq!(state.variable("z"), None);
+    }
+}
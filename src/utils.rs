@@ -1,18 +1,55 @@
+use serde::Serialize;
 use std::fmt;
 
-#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+// The integer type of the language. The `i64` feature widens it for programs that count past
+// two billion.
+#[cfg(not(feature = "i64"))]
+pub type Num = i32;
+#[cfg(feature = "i64")]
+pub type Num = i64;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize)]
 pub struct Span {
     pub start: usize,
     pub end: usize,
 }
 
+impl Span {
+    // The smallest span covering both, in either order
+    pub fn merge(&self, other: &Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+
+    // From the start of this span to the end of `end`, like `while` up to its closing bracket
+    pub fn to(self, end: Span) -> Span {
+        Span {
+            start: self.start,
+            end: end.end,
+        }
+    }
+}
+
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub struct SpanError {
     pub message: String,
     pub span: Span,
 }
 
-fn line_info(text: &str, index: usize) -> (usize, usize, &str) {
+pub fn line_info(text: &str, index: usize) -> (usize, usize, &str) {
+    line_info_with_tab_width(text, index, 1)
+}
+
+// Like `line_info`, but the column is where the character appears on screen. A tab moves to the
+// next multiple of `tab_width`, so with 4 a tab at the start of the line puts the next character
+// in column 5. With 1 every character is one column, 0 is not a valid width.
+pub fn line_info_with_tab_width(
+    text: &str,
+    index: usize,
+    tab_width: usize,
+) -> (usize, usize, &str) {
     let mut line = 1;
     let mut position = 1;
     let mut start = 0;
@@ -28,7 +65,11 @@ fn line_info(text: &str, index: usize) -> (usize, usize, &str) {
             position = 1;
             start = end;
         } else if i < index {
-            position += 1;
+            position = if c == '\t' {
+                (position - 1) / tab_width * tab_width + tab_width + 1
+            } else {
+                position + 1
+            };
         }
     }
 
@@ -36,13 +77,27 @@ fn line_info(text: &str, index: usize) -> (usize, usize, &str) {
 }
 
 pub fn format_error(error: &SpanError, input: &str) -> String {
-    let (line_number, char_number, line) = line_info(input, error.span.start);
+    format_error_with_tab_width(error, input, 1)
+}
+
+pub fn format_error_with_tab_width(error: &SpanError, input: &str, tab_width: usize) -> String {
+    let (line_number, char_number, line) =
+        line_info_with_tab_width(input, error.span.start, tab_width);
     format!(
         "{}, on line {} char {}:\n{}",
         error.message, line_number, char_number, line
     )
 }
 
+// Only the message, `format_error` adds the position when the source is at hand
+impl fmt::Display for SpanError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for SpanError {}
+
 impl SpanError {
     pub fn new(message: String, start: usize, end: usize) -> SpanError {
         SpanError {
@@ -62,8 +117,64 @@ impl From<String> for MainError {
     }
 }
 
+impl fmt::Display for MainError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(&self.message)
+    }
+}
+
+// `main` prints the error with Debug, forwarding to the message keeps it readable
 impl fmt::Debug for MainError {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Display::fmt(&self.message, formatter)
+        fmt::Display::fmt(self, formatter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        format_error_with_tab_width, line_info, line_info_with_tab_width, Span, SpanError,
+    };
+
+    fn span(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+
+    #[test]
+    fn merge() {
+        assert_eq!(span(0, 3).merge(&span(5, 8)), span(0, 8));
+        assert_eq!(span(5, 8).merge(&span(0, 3)), span(0, 8));
+        // Overlapping
+        assert_eq!(span(2, 6).merge(&span(4, 9)), span(2, 9));
+        assert_eq!(span(0, 10).merge(&span(3, 4)), span(0, 10));
+        // Adjacent
+        assert_eq!(span(0, 4).merge(&span(4, 7)), span(0, 7));
+        assert_eq!(span(4, 4).merge(&span(4, 4)), span(4, 4));
+    }
+
+    #[test]
+    fn tab_width() {
+        let text = "var x = 1;\n\tx = y;";
+        // `y` is the 6th character of the second line
+        let index = text.find('y').unwrap();
+
+        assert_eq!(line_info(text, index), (2, 6, "\tx = y;"));
+        assert_eq!(line_info_with_tab_width(text, index, 1), (2, 6, "\tx = y;"));
+        assert_eq!(line_info_with_tab_width(text, index, 4), (2, 9, "\tx = y;"));
+        // A tab after text only fills up to the next tab stop
+        assert_eq!(line_info_with_tab_width("ab\tc", 3, 4), (1, 5, "ab\tc"));
+        assert_eq!(line_info_with_tab_width("abcd\tc", 5, 4), (1, 9, "abcd\tc"));
+
+        let error = SpanError::new("Variable y is not defined".into(), index, index + 1);
+        assert_eq!(
+            format_error_with_tab_width(&error, text, 4),
+            "Variable y is not defined, on line 2 char 9:\n\tx = y;"
+        );
+    }
+
+    #[test]
+    fn to() {
+        assert_eq!(span(0, 5).to(span(10, 11)), span(0, 11));
+        assert_eq!(span(3, 4).to(span(3, 4)), span(3, 4));
     }
 }
@@ -0,0 +1,395 @@
+use crate::{
+    compiler::Instruction,
+    evaluator::{
+        evaluate_operator, syntax_error, unresolved_include, Evaluator, RuntimeError, State, Stop,
+    },
+    lexer::Operator,
+    utils::{Num, Span},
+};
+
+// Executes compiled instructions with an operand stack, sharing `State` with the evaluator
+pub struct Vm {}
+
+impl Vm {
+    pub fn execute(
+        &self,
+        state: &mut State,
+        instructions: &[Instruction],
+    ) -> Result<(), RuntimeError> {
+        // An error can leave loop scopes open
+        let depth = state.variables.depth();
+        state.exit_code = None;
+        let result = self.run(state, instructions);
+        state.variables.truncate(depth);
+        state.finish(result)
+    }
+
+    fn run(&self, state: &mut State, instructions: &[Instruction]) -> Result<(), Stop> {
+        let evaluator = Evaluator::new();
+        let mut stack: Vec<Num> = Vec::new();
+        let mut pointer = 0;
+
+        while let Some(instruction) = instructions.get(pointer) {
+            pointer += 1;
+
+            match instruction {
+                Instruction::PushConst(value) => stack.push(*value),
+                Instruction::Pop => {
+                    pop(&mut stack);
+                }
+                Instruction::Swap => {
+                    let top = pop(&mut stack);
+                    let below = pop(&mut stack);
+                    stack.push(top);
+                    stack.push(below);
+                }
+                Instruction::Over => {
+                    let below = stack[stack.len() - 2];
+                    stack.push(below);
+                }
+                Instruction::LoadVar(name, span) => {
+                    let value = state
+                        .variables
+                        .get(name)
+                        .copied()
+                        .ok_or_else(|| RuntimeError {
+                            message: format!("Variable {name} is not defined"),
+                            span: *span,
+                        })?;
+                    stack.push(value);
+                }
+                Instruction::DefineVar(name, span) => {
+                    let value = pop(&mut stack);
+                    if !state.variables.define(*name, value) {
+                        return Err(RuntimeError {
+                            message: format!("Variable {name} is already defined"),
+                            span: *span,
+                        }
+                        .into());
+                    }
+                }
+                Instruction::DefineConst(name, span) => {
+                    let value = pop(&mut stack);
+                    if !state.variables.define_constant(*name, value) {
+                        return Err(RuntimeError {
+                            message: format!("Variable {name} is already defined"),
+                            span: *span,
+                        }
+                        .into());
+                    }
+                }
+                Instruction::StoreVar(name, span) => {
+                    let value = pop(&mut stack);
+                    state.variables.assign(*name, value, *span)?;
+                }
+                Instruction::Add(span) => binary_operator(&mut stack, Operator::Plus, *span)?,
+                Instruction::Sub(span) => binary_operator(&mut stack, Operator::Minus, *span)?,
+                Instruction::Mul(span) => {
+                    binary_operator(&mut stack, Operator::Multiplication, *span)?
+                }
+                Instruction::Lt(span) => binary_operator(&mut stack, Operator::LessThan, *span)?,
+                Instruction::BitAnd(span) => {
+                    binary_operator(&mut stack, Operator::BitwiseAnd, *span)?
+                }
+                Instruction::BitOr(span) => {
+                    binary_operator(&mut stack, Operator::BitwiseOr, *span)?
+                }
+                Instruction::BitXor(span) => {
+                    binary_operator(&mut stack, Operator::BitwiseXor, *span)?
+                }
+                Instruction::Shl(span) => binary_operator(&mut stack, Operator::ShiftLeft, *span)?,
+                Instruction::Shr(span) => binary_operator(&mut stack, Operator::ShiftRight, *span)?,
+                Instruction::JumpIfZero(target) => {
+                    if pop(&mut stack) == 0 {
+                        pointer = *target;
+                    }
+                }
+                Instruction::JumpUnlessMatches(pattern, target) => {
+                    if !pattern.matches(stack[stack.len() - 1]) {
+                        pointer = *target;
+                    }
+                }
+                Instruction::Jump(target) => pointer = *target,
+                Instruction::SyntaxError(span) => return Err(syntax_error(*span).into()),
+                Instruction::UnresolvedInclude(path, span) => {
+                    return Err(unresolved_include(*path, *span).into())
+                }
+                Instruction::EnterScope => state.variables.push_scope(),
+                Instruction::ExitScope => state.variables.pop_scope(),
+                Instruction::Call(function_call) => {
+                    let value = evaluator.evaluate_function_call(state, function_call)?;
+                    stack.push(value);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn pop(stack: &mut Vec<Num>) -> Num {
+    stack
+        .pop()
+        .expect("compiled instructions keep the stack balanced")
+}
+
+fn binary_operator(
+    stack: &mut Vec<Num>,
+    operator: Operator,
+    span: Span,
+) -> Result<(), RuntimeError> {
+    let right = pop(stack);
+    let left = pop(stack);
+    stack.push(evaluate_operator(operator, left, right, span)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{
+        compiler::compile,
+        evaluator::{Evaluator, RuntimeError, State},
+        lexer::SimpleTokenizer,
+        parser::Parser,
+        runtime::SharedBuffer,
+        utils::Num,
+    };
+
+    use super::Vm;
+
+    // Runs the source with both backends, returning the printed output of each
+    fn run_both(source: &str, input: &'static str) -> [(Result<(), RuntimeError>, String); 2] {
+        let program = Parser::new(SimpleTokenizer::new(source)).parse().unwrap();
+
+        let evaluator_output = SharedBuffer::new();
+        let mut state = State::with_io(Cursor::new(input), evaluator_output.clone());
+        let evaluator_result = Evaluator::new().evaluate_with_state(&mut state, program.clone());
+
+        let vm_output = SharedBuffer::new();
+        let mut state = State::with_io(Cursor::new(input), vm_output.clone());
+        let vm_result = Vm {}.execute(&mut state, &compile(&program));
+
+        [
+            (evaluator_result, evaluator_output.contents()),
+            (vm_result, vm_output.contents()),
+        ]
+    }
+
+    #[test]
+    fn loop_matches_evaluator() {
+        let source = "
+            var n = input();
+            var curr = 0;
+            var next = 1;
+            var tmp = 0;
+            var i = 0;
+            while i < n {
+                tmp = curr;
+                curr = next;
+                next = tmp + next;
+                i = i + 1;
+                print(i * 1000 - curr);
+            }
+            print(curr);
+        ";
+        let [evaluator, vm] = run_both(source, "20\n");
+
+        assert_eq!(evaluator.0, Ok(()));
+        assert!(evaluator.1.ends_with("curr = 6765\n"));
+        assert_eq!(evaluator, vm);
+    }
+
+    #[test]
+    fn loop_scope_matches_evaluator() {
+        let source = "
+            var i = 0;
+            while i < 3 {
+                var tmp = i * 10;
+                print(tmp);
+                i = i + 1;
+            }
+            print(var_count());
+        ";
+        let [evaluator, vm] = run_both(source, "");
+
+        assert_eq!(evaluator.0, Ok(()));
+        assert!(evaluator.1.ends_with("Result = 1\n"));
+        assert_eq!(evaluator, vm);
+    }
+
+    #[test]
+    fn conditional_matches_evaluator() {
+        let source = "
+            var i = 0;
+            while i < 4 {
+                print(i < 2 ? input() : i < 3 ? 20 : 30);
+                i = i + 1;
+            }
+        ";
+        let [evaluator, vm] = run_both(source, "1\n2\n3\n");
+
+        assert_eq!(evaluator.0, Ok(()));
+        assert_eq!(evaluator, vm);
+    }
+
+    #[test]
+    fn comparison_chain_matches_evaluator() {
+        let source = "
+            var n = 0;
+            print(1 < 2 < 3, 1 < 3 < 2, 3 < 2 < (n = 1), 0 < (n = n + 1) < 2 < 5, n);
+        ";
+        let [evaluator, vm] = run_both(source, "");
+
+        assert_eq!(evaluator.0, Ok(()));
+        assert_eq!(evaluator.1, "1 0 0 1 1\n");
+        assert_eq!(evaluator, vm);
+    }
+
+    #[test]
+    fn expression_statement_matches_evaluator() {
+        let [evaluator, vm] = run_both(
+            "var x = 1; x + 1; input() * 2; (x = 3) < 4 ? print(x) : 0;",
+            "7\n",
+        );
+
+        assert_eq!(evaluator.0, Ok(()));
+        assert_eq!(evaluator.1, "Input: \nx = 3\n");
+        assert_eq!(evaluator, vm);
+    }
+
+    #[test]
+    fn do_while_matches_evaluator() {
+        let source = "
+            var i = 0;
+            do { var twice = i * 2; print(twice); i = i + 1; } while (i < 3);
+            do { print(i); } while (0);
+        ";
+        let [evaluator, vm] = run_both(source, "");
+
+        assert_eq!(evaluator.0, Ok(()));
+        assert_eq!(evaluator.1, "twice = 0\ntwice = 2\ntwice = 4\ni = 3\n");
+        assert_eq!(evaluator, vm);
+    }
+
+    #[test]
+    fn match_matches_evaluator() {
+        let source = "
+            var i = 0;
+            while i < 4 {
+                match i { 1 => { var one = 1; print(one); } 2 => { } _ => { print(i * 10); } }
+                match i { 3 => { print(i); } }
+                i = i + 1;
+            }
+        ";
+        let [evaluator, vm] = run_both(source, "");
+
+        assert_eq!(evaluator.0, Ok(()));
+        assert_eq!(evaluator.1, "Result = 0\none = 1\nResult = 30\ni = 3\n");
+        assert_eq!(evaluator, vm);
+    }
+
+    #[test]
+    fn loop_condition_matches_evaluator() {
+        let [evaluator, vm] = run_both(
+            "var runs = 0; while input() < 3 { runs = runs + 1; } print(runs);",
+            "0\n1\n2\n3\n4\n",
+        );
+
+        assert_eq!(evaluator.0, Ok(()));
+        assert_eq!(evaluator.1.matches("Input: ").count(), 4);
+        assert_eq!(evaluator, vm);
+    }
+
+    #[test]
+    fn exit_matches_evaluator() {
+        let [evaluator, vm] = run_both(
+            "var i = 0; while 1 { print(i); i = i + 1; match i { 2 => { exit(); } } }",
+            "",
+        );
+
+        assert_eq!(evaluator.0, Ok(()));
+        assert_eq!(evaluator.1, "i = 0\ni = 1\n");
+        assert_eq!(evaluator, vm);
+
+        let [evaluator, vm] = run_both("print(1); exit(300);", "");
+
+        assert!(evaluator.0.is_err());
+        assert_eq!(evaluator, vm);
+    }
+
+    #[test]
+    fn empty_bodies_match_evaluator() {
+        let [evaluator, vm] = run_both(
+            "while 0 { } do { } while (0); match 2 { 1 => { } _ => { } } while input() < 2 { } print(1);",
+            "1\n2\n",
+        );
+
+        assert_eq!(evaluator.0, Ok(()));
+        assert_eq!(evaluator, vm);
+    }
+
+    #[test]
+    fn multi_assignment_matches_evaluator() {
+        let [evaluator, vm] = run_both(
+            "var a, b, c = 1, 2, 3; var i = 0; while i < 1 { var a, b = b, a; print(a, b, c); i = 1; }",
+            "",
+        );
+
+        assert_eq!(evaluator.0, Ok(()));
+        assert_eq!(evaluator.1, "2 1 3\n");
+        assert_eq!(evaluator, vm);
+    }
+
+    #[test]
+    fn defined_matches_evaluator() {
+        let [evaluator, vm] = run_both(
+            "var x = 1; var i = 0; while i < 1 { var y = 2; i = 1; print(defined(x), defined(y)); } \
+             print(defined(y));",
+            "",
+        );
+
+        assert_eq!(evaluator.0, Ok(()));
+        assert_eq!(evaluator.1, "1 1\nResult = 0\n");
+        assert_eq!(evaluator, vm);
+    }
+
+    #[test]
+    fn errors_match_evaluator() {
+        let [evaluator, vm] = run_both("var x = 1; print(x); x = y;", "");
+
+        assert!(evaluator.0.is_err());
+        assert_eq!(evaluator, vm);
+
+        let [evaluator, vm] = run_both("var x = 1; var x = 2;", "");
+
+        assert!(evaluator.0.is_err());
+        assert_eq!(evaluator, vm);
+
+        let [evaluator, vm] = run_both("const x = 1; print(x); x = 2;", "");
+
+        assert!(evaluator.0.is_err());
+        assert_eq!(evaluator, vm);
+
+        let [evaluator, vm] = run_both(&format!("var x = {}; x = x + 1;", Num::MAX), "");
+
+        assert!(evaluator.0.is_err());
+        assert_eq!(evaluator, vm);
+    }
+
+    #[test]
+    fn undefined_variable_message() {
+        // Reading, assigning and updating report the same message
+        for source in ["print(y);", "y = 1;", "y += 5;"] {
+            let [evaluator, vm] = run_both(source, "");
+
+            assert_eq!(
+                evaluator.0.as_ref().unwrap_err().message,
+                "Variable y is not defined",
+                "{source}"
+            );
+            assert_eq!(evaluator, vm, "{source}");
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use std::io;
+
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::{
+    evaluator::{Evaluator, RuntimeError, State, Stop},
+    parser::{AstNode, FunctionCall},
+    run_with_state,
+    runtime::SharedBuffer,
+    utils::{format_error, Num},
+};
+
+// Entry point for running scripts in the browser. The output of the program is captured and
+// returned, followed by the formatted error if the program fails. `rand` is seeded by the caller,
+// the page can pass `Date.now()` for a new sequence on every run.
+#[wasm_bindgen]
+pub fn run_source(source: &str, seed: u64) -> String {
+    let output = SharedBuffer::new();
+    let mut state = State::with_io(io::empty(), output.clone());
+    state.set_seed(seed);
+    // There is no console to read from
+    state.register_function("input", function_no_input);
+    state.register_function("input_radix", function_no_input);
+
+    let result = run_with_state(source, &mut state);
+
+    let mut text = output.contents();
+    if let Err(error) = result {
+        text.push_str(&format_error(&error, source));
+    }
+    text
+}
+
+fn function_no_input(
+    _: &Evaluator,
+    _: &mut State,
+    AstNode { span, .. }: &AstNode<FunctionCall>,
+) -> Result<Num, Stop> {
+    Err(RuntimeError {
+        message: "Input is not available in the browser".into(),
+        span: *span,
+    }
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::run_source;
+
+    #[test]
+    fn captures_output() {
+        assert_eq!(run_source("var x = 2;\nprint(x * 3);", 1), "Result = 6\n");
+    }
+
+    #[test]
+    fn seeded_rand() {
+        let source = "print(rand(0, 1000), rand(0, 1000));";
+
+        assert_eq!(run_source(source, 7), run_source(source, 7));
+        assert_ne!(run_source(source, 7), run_source(source, 8));
+    }
+
+    #[test]
+    fn error_after_output() {
+        assert_eq!(
+            run_source("print(1);\nvar y = input();", 1),
+            "Result = 1\nInput is not available in the browser, on line 2 char 9:\nvar y = input();"
+        );
+    }
+}
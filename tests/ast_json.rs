@@ -0,0 +1,21 @@
+use simple_interpreter::{Parser, SimpleTokenizer};
+
+#[test]
+fn assignment_to_json() {
+    let program = Parser::new(SimpleTokenizer::new("var x = 1 + 2;"))
+        .parse()
+        .unwrap();
+    let json = serde_json::to_string(&program).unwrap();
+
+    let expression = concat!(
+        r#"{"node":{"BinaryOperator":["#,
+        r#"{"node":{"Number":1},"span":{"start":8,"end":9}},"#,
+        r#""Plus","#,
+        r#"{"node":{"Number":2},"span":{"start":12,"end":13}}"#,
+        r#"]},"span":{"start":8,"end":13}}"#,
+    );
+
+    assert!(json.contains(&format!(r#"{{"Assignment":["x",{expression}]}}"#)));
+    assert!(json.starts_with(r#"{"node":{"lines":["#));
+    assert!(json.ends_with(r#""span":{"start":0,"end":14}}"#));
+}
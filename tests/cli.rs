@@ -0,0 +1,306 @@
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{self, Command, Stdio};
+
+// The process id keeps runs of the tests at the same time from sharing files
+fn temp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("simple-interpreter-{}-{name}.txt", process::id()))
+}
+
+// Runs the interpreter binary on the source, returning stdout
+fn run_cli(name: &str, source: &str, args: &[&str]) -> String {
+    let path = temp_path(name);
+    fs::write(&path, source).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_simple-interpreter"))
+        .args(args)
+        .arg(&path)
+        .output()
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn tokens() {
+    let output = run_cli("tokens", "var x = 1;", &["--tokens"]);
+
+    assert_eq!(
+        output,
+        "0..3 Var\n4..5 Identifier(\"x\")\n6..7 Equals\n8..9 Number(1)\n9..10 SemiColon\n10..10 Eof\n"
+    );
+}
+
+#[test]
+fn ast() {
+    let output = run_cli("ast", "print(1 + 2 * 3);", &["--ast"]);
+
+    assert_eq!(
+        output,
+        "Program\n  Call print\n    BinaryOperator +\n      Number 1\n      BinaryOperator *\n        Number 2\n        Number 3\n"
+    );
+}
+
+#[test]
+fn dot() {
+    let output = run_cli("dot", "var x = 1 + 2;", &["--dot"]);
+
+    assert!(output.starts_with("digraph ast {\n  n0 [label=\"Program\"];\n"));
+    assert!(output.contains("n2 [label=\"BinaryOperator +\"];"));
+    assert_eq!(output.matches(" -> ").count(), 4);
+    assert!(output.ends_with("}\n"));
+}
+
+#[test]
+fn emit_debug() {
+    let output = run_cli(
+        "emit-debug",
+        "var x = 1 + 2; while x { print(x); }",
+        &["--parse-only", "--emit=debug"],
+    );
+
+    for expected in [
+        "Program",
+        "Assignment",
+        "BinaryOperator",
+        "Plus",
+        "Loop",
+        "Call",
+        "\"print\"",
+    ] {
+        assert!(output.contains(expected), "{expected} is missing");
+    }
+    assert!(!output.contains("Starting..."));
+}
+
+#[test]
+fn program_from_stdin() {
+    for args in [&[][..], &["-"][..]] {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_simple-interpreter"))
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(b"var x = 1 + 2;\nprint(x);\n")
+            .unwrap();
+        let output = child.wait_with_output().unwrap();
+
+        assert!(output.status.success());
+        assert!(String::from_utf8(output.stdout)
+            .unwrap()
+            .contains("x = 3\n"));
+    }
+}
+
+#[test]
+fn exit_code() {
+    let path = temp_path("exit-code");
+    fs::write(&path, "print(1);\nexit(3);\nprint(2);").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_simple-interpreter"))
+        .arg(&path)
+        .output()
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Parsing...\nStarting...\nResult = 1\n"
+    );
+}
+
+fn run_diagnostics(name: &str, source: &str) -> serde_json::Value {
+    let path = temp_path(name);
+    fs::write(&path, source).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_simple-interpreter"))
+        .arg("--diagnostics=json")
+        .arg(&path)
+        .output()
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    serde_json::from_slice(&output.stderr).unwrap()
+}
+
+#[test]
+fn json_diagnostics() {
+    let diagnostics = run_diagnostics("diagnostics", "var x = ;\nvar y = 1;\ny = ;");
+
+    assert_eq!(
+        diagnostics,
+        serde_json::json!([
+            {
+                "message": "Unexpected token ';', expected number, boolean, opening parenthesis, identifier",
+                "start_line": 1,
+                "start_col": 9,
+                "end_line": 1,
+                "end_col": 10,
+                "severity": "error"
+            },
+            {
+                "message": "Unexpected token ';', expected number, boolean, opening parenthesis, identifier",
+                "start_line": 3,
+                "start_col": 5,
+                "end_line": 3,
+                "end_col": 6,
+                "severity": "error"
+            }
+        ])
+    );
+}
+
+#[test]
+fn json_diagnostics_runtime_error() {
+    let diagnostics = run_diagnostics("diagnostics-runtime", "var x = 1;\nprint(x, y);");
+
+    assert_eq!(
+        diagnostics,
+        serde_json::json!([{
+            "message": "Variable y is not defined",
+            "start_line": 2,
+            "start_col": 10,
+            "end_line": 2,
+            "end_col": 11,
+            "severity": "error"
+        }])
+    );
+}
+
+#[test]
+fn json_diagnostics_warnings() {
+    // Warnings and the error are one array
+    let diagnostics = run_diagnostics("diagnostics-warnings", "var max = 1;\nprint(y);");
+
+    assert_eq!(
+        diagnostics,
+        serde_json::json!([
+            {
+                "message": "Variable max has the same name as a function",
+                "start_line": 1,
+                "start_col": 1,
+                "end_line": 1,
+                "end_col": 13,
+                "severity": "warning"
+            },
+            {
+                "message": "Variable y is not defined",
+                "start_line": 2,
+                "start_col": 7,
+                "end_line": 2,
+                "end_col": 8,
+                "severity": "error"
+            }
+        ])
+    );
+
+    // Without an error the program succeeds and only the warnings are printed
+    let path = temp_path("diagnostics-warnings-only");
+    fs::write(&path, "var max = 1;\nprint(max);").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_simple-interpreter"))
+        .arg("--diagnostics=json")
+        .arg(&path)
+        .output()
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert!(output.status.success());
+    let diagnostics: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+    assert_eq!(diagnostics[0]["severity"], "warning");
+}
+
+#[test]
+fn number_too_large() {
+    // Reported like any other error instead of a panic
+    let diagnostics = run_diagnostics("number-too-large", "var x = 99999999999999999999;");
+
+    assert_eq!(
+        diagnostics,
+        serde_json::json!([{
+            "message": "Number literal too large",
+            "start_line": 1,
+            "start_col": 9,
+            "end_line": 1,
+            "end_col": 29,
+            "severity": "error"
+        }])
+    );
+}
+
+#[test]
+fn warn_shadowed_function() {
+    let path = temp_path("warn-shadowed");
+    fs::write(&path, "var x = 1;\nvar max = 2;\nprint(max(x, max));").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_simple-interpreter"))
+        .arg(&path)
+        .output()
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("Warning: Variable max has the same name as a function"));
+    assert!(stderr.contains("on line 2 char 1"));
+    assert!(!stderr.contains("Variable x"));
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("Result = 2\nSuccess!"));
+}
+
+#[test]
+fn warn_unused() {
+    let path = temp_path("warn-unused");
+    fs::write(&path, "var used = 1;\nvar unused = 2;\nprint(used);").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_simple-interpreter"))
+        .arg("--warn-unused")
+        .arg(&path)
+        .output()
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("Warning: Variable unused is never used"));
+    assert!(stderr.contains("on line 2 char 1"));
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("used = 1\nSuccess!"));
+}
+
+#[test]
+fn combined_flags() {
+    let path = temp_path("combined-flags");
+    fs::write(&path, "var unused = 1\nvar i = 0\nwhile 1 { i = i + 1 }\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_simple-interpreter"))
+        .args([
+            "--warn-unused",
+            "--max-steps",
+            "20",
+            "--optional-semicolons",
+        ])
+        .arg(&path)
+        .output()
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("Warning: Variable unused is never used"));
+    assert!(stderr.contains("Execution budget exceeded"));
+}
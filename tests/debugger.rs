@@ -0,0 +1,63 @@
+use std::io::Cursor;
+
+use simple_interpreter::{
+    debugger::run_debugger, Evaluator, Parser, SharedBuffer, SimpleTokenizer, State,
+};
+
+fn debug(source: &str, commands: &str) -> (Result<(), String>, String, State<'static>) {
+    let program = Parser::new(SimpleTokenizer::new(source)).parse().unwrap();
+    let mut state = State::with_io(Cursor::new("7\n"), SharedBuffer::new());
+    let mut output = Vec::new();
+
+    let result = run_debugger(
+        source,
+        program,
+        &mut state,
+        &Evaluator::new(),
+        &mut Cursor::new(commands),
+        &mut output,
+    );
+
+    (result, String::from_utf8(output).unwrap(), state)
+}
+
+#[test]
+fn step_and_print() {
+    let (result, output, state) = debug(
+        "var x = input();\nx = x * 2;\nvar y = 1;",
+        "print x\nstep\nprint x\nstep\nprint x\nquit\n",
+    );
+
+    assert_eq!(result, Ok(()));
+    assert_eq!(
+        output,
+        "\
+var x = input();
+(debug) Variable x is not defined
+(debug) x = x * 2;
+(debug) x = 7
+(debug) var y = 1;
+(debug) x = 14
+(debug) "
+    );
+    assert_eq!(state.variable("y"), None);
+}
+
+#[test]
+fn continue_to_the_end() {
+    let (result, output, state) = debug("var x = 1;\nwhile x < 5 { x = x + 1; }", "continue\n");
+
+    assert_eq!(result, Ok(()));
+    assert_eq!(output, "var x = 1;\n(debug) ");
+    assert_eq!(state.variable("x"), Some(5));
+}
+
+#[test]
+fn runtime_error_stops_debugger() {
+    let (result, _, _) = debug("var x = 1;\ny = 2;", "step\nstep\n");
+
+    assert_eq!(
+        result,
+        Err("Variable y is not defined, on line 2 char 1:\ny = 2;".to_string())
+    );
+}
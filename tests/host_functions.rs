@@ -0,0 +1,42 @@
+use simple_interpreter::{
+    parser::{AstNode, FunctionCall},
+    run_with_state, Evaluator, Num, RuntimeError, State, Stop,
+};
+
+fn function_double(
+    evaluator: &Evaluator,
+    state: &mut State,
+    AstNode {
+        node: function_call,
+        span,
+    }: &AstNode<FunctionCall>,
+) -> Result<Num, Stop> {
+    match function_call.arguments.as_slice() {
+        [argument] => Ok(evaluator.evaluate_expression(state, argument)? * 2),
+        _ => Err(RuntimeError {
+            message: "double expects a single argument".into(),
+            span: *span,
+        }
+        .into()),
+    }
+}
+
+#[test]
+fn call_registered_function() {
+    let mut state = State::new();
+    state.register_function("double", function_double);
+
+    run_with_state("var x = double(20 + 1);", &mut state).unwrap();
+
+    assert_eq!(state.variable("x"), Some(42));
+}
+
+#[test]
+fn registered_function_errors() {
+    let mut state = State::new();
+    state.register_function("double", function_double);
+
+    let error = run_with_state("var x = double();", &mut state).unwrap_err();
+
+    assert_eq!(error.message, "double expects a single argument");
+}
@@ -0,0 +1,105 @@
+use std::{env, fs, path::PathBuf, process};
+
+use simple_interpreter::{
+    include::Loader, Evaluator, Parser, SharedBuffer, SimpleTokenizer, State,
+};
+
+// A fresh directory with the given files, the process id keeps concurrent runs apart
+fn write_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let directory = env::temp_dir().join(format!("simple-interpreter-{}-{name}", process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(directory.join("lib")).unwrap();
+    for (path, text) in files {
+        fs::write(directory.join(path), text).unwrap();
+    }
+    directory
+}
+
+#[test]
+fn include_splices_lines() {
+    let directory = write_files(
+        "include",
+        &[
+            (
+                "main.txt",
+                "var x = 1;\ninclude \"lib/helper.txt\";\nprint(x + answer);\n",
+            ),
+            // Relative to the including file
+            ("lib/helper.txt", "include \"constants.txt\";\nx = x * 2;\n"),
+            ("lib/constants.txt", "const answer = 40;\n"),
+        ],
+    );
+
+    let mut loader = Loader::new();
+    let program = loader.load(&directory.join("main.txt")).unwrap();
+
+    assert_eq!(program.node.lines.len(), 4);
+
+    let output = SharedBuffer::new();
+    let mut state = State::with_io(std::io::empty(), output.clone());
+    Evaluator::new()
+        .evaluate_with_state(&mut state, program)
+        .unwrap();
+
+    assert_eq!(output.contents(), "Result = 42\n");
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn errors_point_into_the_included_file() {
+    let directory = write_files(
+        "include-error",
+        &[
+            ("main.txt", "include \"lib/helper.txt\";\nprint(y);\n"),
+            ("lib/helper.txt", "var x = 1;\nx = y;\n"),
+        ],
+    );
+
+    let mut loader = Loader::new();
+    let program = loader.load(&directory.join("main.txt")).unwrap();
+    let error = Evaluator::new()
+        .evaluate_with_state(&mut State::new(), program)
+        .unwrap_err();
+    let message = loader.source_map().format_error(&error);
+
+    assert!(
+        message.starts_with("Variable y is not defined, in "),
+        "{message}"
+    );
+    assert!(
+        message.ends_with("helper.txt on line 2 char 5:\nx = y;\n"),
+        "{message}"
+    );
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn include_cycle() {
+    let directory = write_files(
+        "include-cycle",
+        &[
+            ("a.txt", "var a = 1;\ninclude \"b.txt\";\n"),
+            ("b.txt", "include \"a.txt\";\n"),
+        ],
+    );
+
+    let error = Loader::new().load(&directory.join("a.txt")).unwrap_err();
+
+    assert_eq!(error.message, "Include cycle: a.txt -> b.txt -> a.txt");
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn include_needs_a_loader() {
+    let program = Parser::new(SimpleTokenizer::new("include \"lib.txt\";"))
+        .parse()
+        .unwrap();
+    let error = Evaluator::new()
+        .evaluate_with_state(&mut State::new(), program)
+        .unwrap_err();
+
+    assert_eq!(
+        error.message,
+        "Cannot include lib.txt, the program was not loaded from a file"
+    );
+}
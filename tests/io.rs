@@ -0,0 +1,25 @@
+use std::io::Cursor;
+
+use simple_interpreter::{run_with_state, SharedBuffer, State};
+
+#[test]
+fn captured_input_and_output() {
+    let output = SharedBuffer::new();
+    let mut state = State::with_io(Cursor::new("21\n"), output.clone());
+
+    run_with_state("var n = input(); print(n * 2);", &mut state).unwrap();
+
+    assert_eq!(output.contents(), "Input: \nResult = 42\n");
+}
+
+#[test]
+fn captured_input_is_not_a_number() {
+    let output = SharedBuffer::new();
+    let mut state = State::with_io(Cursor::new("abc\n"), output.clone());
+
+    let error = run_with_state("var n = input();", &mut state).unwrap_err();
+
+    assert!(error
+        .message
+        .starts_with("Cannot convert string to integer: abc"));
+}
@@ -0,0 +1,60 @@
+use simple_interpreter::{analyze_and_run, RunOptions};
+
+#[test]
+fn report_warnings_and_execution() {
+    let report = analyze_and_run(
+        "var n = input(); var unused = 1; print(n * 2);",
+        RunOptions {
+            input: "21\n".into(),
+            ..RunOptions::default()
+        },
+    );
+
+    assert_eq!(report.parse_error, None);
+    assert_eq!(report.warnings.len(), 1);
+    assert_eq!(report.warnings[0].message, "Variable unused is never used");
+
+    let execution = report.execution.unwrap();
+    assert_eq!(execution.result, Ok(()));
+    assert_eq!(execution.output, "Input: \nResult = 42\n");
+    assert_eq!(execution.state.variable("unused"), Some(1));
+}
+
+#[test]
+fn report_without_lints() {
+    let report = analyze_and_run(
+        "var unused = 1;",
+        RunOptions {
+            lints: vec![],
+            ..RunOptions::default()
+        },
+    );
+
+    assert!(report.warnings.is_empty());
+    assert!(report.execution.is_some());
+}
+
+#[test]
+fn report_parse_error() {
+    let report = analyze_and_run("var = 1;", RunOptions::default());
+
+    assert_eq!(
+        report.parse_error.unwrap().message,
+        "Unexpected token '=', expected: identifier"
+    );
+    assert!(report.execution.is_none());
+}
+
+#[test]
+fn report_step_budget() {
+    let report = analyze_and_run(
+        "while 1 { }",
+        RunOptions {
+            max_steps: Some(5),
+            ..RunOptions::default()
+        },
+    );
+
+    let error = report.execution.unwrap().result.unwrap_err();
+    assert_eq!(error.message, "Execution budget exceeded");
+}
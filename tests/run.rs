@@ -0,0 +1,147 @@
+use std::error::Error;
+
+use simple_interpreter::{
+    parser::{AstNode, FunctionCall},
+    run, run_catch_unwind, run_catch_unwind_with_state, run_with_state,
+    utils::{format_error, MainError, Span},
+    Evaluator, Num, RunError, SharedBuffer, State, Stop,
+};
+
+#[test]
+fn run_program() {
+    let source = "
+        var i = 0;
+        var sum = 0;
+        while i < 10 {
+            sum = sum + i;
+            i = i + 1;
+        }
+    ";
+
+    assert_eq!(run(source), Ok(()));
+}
+
+#[test]
+fn run_reports_parser_error() {
+    let error = run("var = 1;").unwrap_err();
+
+    assert_eq!(error.span, Span { start: 4, end: 5 });
+}
+
+#[test]
+fn run_reports_runtime_error() {
+    let error = run("x = 1;").unwrap_err();
+
+    assert_eq!(error.message, "Variable x is not defined");
+    assert_eq!(error.span, Span { start: 0, end: 6 });
+}
+
+#[test]
+fn run_reports_error_at_end_of_input() {
+    let source = "var x = 1;\nvar y = x +";
+    let error = run(source).unwrap_err();
+
+    assert_eq!(error.span, Span { start: 22, end: 22 });
+    assert_eq!(
+        format_error(&error, source),
+        "Unexpected token 'end of input', expected number, boolean, opening parenthesis, identifier, on line 2 char 12:\nvar y = x +"
+    );
+
+    let source = "var y = x +\n";
+    let error = run(source).unwrap_err();
+
+    assert_eq!(
+        format_error(&error, source),
+        "Unexpected token 'end of input', expected number, boolean, opening parenthesis, identifier, on line 2 char 1:\n"
+    );
+}
+
+#[test]
+fn run_catch_unwind_reports_program_errors() {
+    assert_eq!(run_catch_unwind("var x = 1;"), Ok(()));
+
+    let error = run_catch_unwind("x = 1;").unwrap_err();
+    assert!(
+        matches!(error, RunError::Program(error) if error.message == "Variable x is not defined")
+    );
+}
+
+#[test]
+fn exit_stops_the_program() {
+    let output = SharedBuffer::new();
+    let mut state = State::with_io(std::io::empty(), output.clone());
+
+    let result = run_with_state("print(1); exit(3); print(2);", &mut state);
+
+    assert_eq!(result, Ok(()));
+    assert_eq!(state.exit_code, Some(3));
+    assert_eq!(output.contents(), "Result = 1\n");
+
+    run_with_state(
+        "var i = 0; while 1 { i = i + 1; i < 5 ? 0 : exit(); }",
+        &mut state,
+    )
+    .unwrap();
+    assert_eq!(state.exit_code, Some(0));
+    assert_eq!(state.variable("i"), Some(5));
+
+    // A finished program does not keep the code of the previous one
+    run_with_state("print(3);", &mut state).unwrap();
+    assert_eq!(state.exit_code, None);
+
+    let error = run_with_state("exit(1, 2);", &mut state).unwrap_err();
+    assert_eq!(
+        error.message,
+        "Wrong number of arguments for exit. Expected 0 or 1, got 2"
+    );
+
+    for (source, code) in [("exit(256);", 256), ("exit(0 - 1);", -1)] {
+        let error = run_with_state(source, &mut state).unwrap_err();
+        assert_eq!(
+            error.message,
+            format!("Exit code out of range: {code}, expected 0 to 255")
+        );
+        assert_eq!(state.exit_code, None);
+    }
+}
+
+#[test]
+fn run_catch_unwind_catches_panics() {
+    fn function_panic(
+        _: &Evaluator,
+        _: &mut State,
+        _: &AstNode<FunctionCall>,
+    ) -> Result<Num, Stop> {
+        panic!("Host function failed")
+    }
+
+    let mut state = State::new();
+    state.register_function("fail", function_panic);
+    let error = run_catch_unwind_with_state("var x = fail();", &mut state).unwrap_err();
+
+    assert_eq!(error, RunError::Internal("Host function failed".into()));
+
+    // Errors of the program are not internal
+    let error = run_catch_unwind("var x = 99999999999999999999;").unwrap_err();
+
+    assert!(
+        matches!(error, RunError::Program(error) if error.message == "Number literal too large")
+    );
+}
+
+#[test]
+fn errors_implement_error() {
+    fn run_boxed(source: &str) -> Result<(), Box<dyn Error>> {
+        run(source)?;
+        Ok(())
+    }
+
+    let error = run_boxed("var x = 1; x = y;").unwrap_err();
+
+    assert_eq!(error.to_string(), "Variable y is not defined");
+
+    let error = MainError::from("Can not read file".to_string());
+
+    assert_eq!(format!("{error}"), "Can not read file");
+    assert_eq!(format!("{error:?}"), "Can not read file");
+}
@@ -0,0 +1,28 @@
+use std::io::Cursor;
+
+use simple_interpreter::session::{record_session, replay_session, Session};
+
+const PROGRAM: &str = "var a = input(); var b = input(); print(a + b); print(a * b);";
+
+#[test]
+fn record_and_replay() {
+    let (session, result) = record_session(PROGRAM, Cursor::new("3\n4\n"));
+    assert_eq!(result, Ok(()));
+    assert_eq!(session.inputs, vec!["3", "4"]);
+
+    let path = std::env::temp_dir().join(format!("session-{}.json", std::process::id()));
+    session.save(&path).unwrap();
+    let loaded = Session::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded, session);
+    assert_eq!(replay_session(&loaded), Ok(()));
+}
+
+#[test]
+fn replay_detects_different_output() {
+    let (mut session, _) = record_session(PROGRAM, Cursor::new("3\n4\n"));
+    session.inputs[1] = "5".into();
+
+    assert!(replay_session(&session).is_err());
+}